@@ -0,0 +1,11 @@
+// Data shared by every program, updated once per frame. Must match `FrameData` in `src/render/frame.rs`.
+layout (std140) uniform FrameData {
+    mat4 view;
+    mat4 projection;
+    vec4 fogColour;
+    vec4 sunDirection;  // Direction the sunlight travels in (w is unused)
+    vec4 sunColour;
+    float time;         // Seconds since the game started
+    float fogStart;
+    float fogEnd;
+};
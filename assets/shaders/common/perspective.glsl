@@ -0,0 +1,17 @@
+// Apply the given perspective to the given coordinates
+vec4 applyPerspective(vec3 coord, float fov, float near, float far, float aspectRatio) {
+    if(far == near) return vec4(-2,-2,-2, 2);   // Error, draw out of bounds
+    
+    float arcTanFov = atan(fov/2);
+
+
+    vec4 newCoords;
+
+    newCoords[3] = (arcTanFov * near * far)/(coord[2] - near);
+
+    newCoords[0] = coord[0];
+    newCoords[1] = coord[1] * aspectRatio;
+    newCoords[2] = ((far + near - (2 * coord[2]))/(far - near)) * ((arcTanFov * near * far)/(coord[2] - near));
+
+    return newCoords;
+}
@@ -0,0 +1,38 @@
+// Quaternion maths
+
+vec4 multiplyQuat(vec4 a, vec4 b) {
+
+    /**
+        (a + ib + jc + kd) * (e + if + jg + kh). 
+    
+        rules:
+        i*i = j*j = k*k = -1
+        i*j = k,
+        j*i = -k
+        j*k = i,
+        k*j = -i
+        k*i = j,
+        i*k = -j
+     */
+    //  ae    + af  i + ag  j + ah  k
+    // +be  i + bf ii + bg ij + bh ik 
+    // +ce  j + cf ji + cg jj + ch jk 
+    // +de  k + df ki + dg kj + dh kk 
+    // ->
+    //  ae    + af  i + ag  j + ah  k
+    // +be  i + bf -1 + bg  k + bh -j 
+    // +ce  j + cf -k + cg -1 + ch  i 
+    // +de  k + df  j + dg -i + dh -1 
+    // ->
+    //     ae - bf - cg - dh 
+    // + i(af + be + ch - dg)
+    // + j(ag - bh + ce + df)
+    // + k(ah + bg - cf + de)
+
+    return vec4(
+        (a[0] * b[0]) - (a[1] * b[1]) - (a[2] * b[2]) - (a[3] * b[3]),  // real
+        (a[0] * b[1]) + (a[1] * b[0]) + (a[2] * b[3]) - (a[3] * b[2]),  // i
+        (a[0] * b[2]) - (a[1] * b[3]) + (a[2] * b[0]) + (a[3] * b[1]),  // j
+        (a[0] * b[3]) + (a[1] * b[2]) - (a[2] * b[1]) + (a[3] * b[0])   // k
+    );
+}
@@ -0,0 +1,56 @@
+// Rotations, using quaternions
+#include "quat.glsl"
+
+// Function to rotate a coordinate around a unit axis
+// Note: rotation is warped if axis is not a unit length vector (eg/ if sqrt(x^2 + y^2 + z^2) != 1)
+vec3 rotate(vec3 coord, vec3 axis, float angle) {
+    float sinAngle = sin(angle/2);
+    float cosAngle = cos(angle/2);
+
+    vec4 q = vec4( 
+        cosAngle, 
+        sinAngle*axis[0],
+        sinAngle*axis[1],
+        sinAngle*axis[2]
+    );
+
+    vec4 qPrime = vec4(
+        cosAngle,
+        -sinAngle * axis[0],
+        -sinAngle * axis[1],
+        -sinAngle * axis[2]
+    );
+
+    vec4 p = vec4(
+        0,
+        coord[0],
+        coord[1],
+        coord[2]
+    );
+
+    vec4 res =  multiplyQuat(multiplyQuat(q,p),qPrime);
+
+    // return the non-real parts
+    return vec3(
+        res[1],
+        res[2],
+        res[3]
+    );
+}
+
+// Rotates in the order X->Y->Z
+vec3 applyRotation(vec3 coord, vec3 rotations) {
+    return rotate(
+        rotate(
+            rotate(
+                coord,
+                vec3(1,0,0),
+                rotations[0]
+            ),
+            vec3(0,1,0),
+            rotations[1]
+        ),
+        vec3(0,0,1),
+        rotations[2]
+    );
+}
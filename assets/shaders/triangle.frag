@@ -4,13 +4,25 @@ in VERT_SHAD_OUTPUT {
     vec2 TexCoord;
 } IN;
 
-uniform float timed_colour;
+#include "common/frame.glsl"
+
 uniform sampler2D ourTexture;
 
 out vec4 Color;
 
 void main()
 {
-    // Color = vec4(0.0f, abs(sin(timed_colour)), 0.0f, 1.0f);   // Varying green colour
+    // Color = vec4(0.0f, abs(sin(time)), 0.0f, 1.0f);   // Varying green colour
     Color = texture(ourTexture, IN.TexCoord);  // From texture
+
+#ifdef ALPHA_CUTOUT
+    if (Color.a < 0.5) {
+        discard;
+    }
+#endif
+
+#ifdef FOG
+    float fog = clamp((gl_FragCoord.z / gl_FragCoord.w - fogStart) / (fogEnd - fogStart), 0.0, 1.0);
+    Color.rgb = mix(Color.rgb, fogColour.rgb, fog);
+#endif
 }
\ No newline at end of file
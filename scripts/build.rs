@@ -2,6 +2,7 @@ use std::env;
 use std::path::PathBuf;
 
 mod copy_assets;
+mod embed_assets;
 
 fn main() {
 
@@ -49,5 +50,8 @@ fn main() {
     //-- End of SDL build script --//
 
     // -- Copy assets to target -- //
-    copy_assets::copy_assets()
+    copy_assets::copy_assets();
+
+    // -- Embed assets into the binary, as a fallback -- //
+    embed_assets::embed_assets()
 }
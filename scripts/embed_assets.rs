@@ -0,0 +1,41 @@
+extern crate walkdir;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use walkdir::WalkDir;
+
+/**
+Generate `$OUT_DIR/embedded_assets.rs`, which lists every file in `/assets` as a
+(resource name, contents) pair, so the assets can be compiled into the binary.
+*/
+pub fn embed_assets() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let assets_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("assets");
+
+    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+    for entry in WalkDir::new(&assets_dir) {
+        let entry = entry.unwrap();
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        if let Ok(rel_path) = entry.path().strip_prefix(&assets_dir) {
+            // Resource names are always posix style
+            let name = rel_path.components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<String>>()
+                .join("/");
+            entries.push((name, entry.path().into()));
+        }
+    }
+    entries.sort();
+
+    let mut code = String::from("pub static EMBEDDED_ASSETS: &[(&str, &[u8])] = &[\n");
+    for (name, path) in entries {
+        code.push_str(&format!("    ({:?}, include_bytes!({:?})),\n", name, path.to_string_lossy()));
+    }
+    code.push_str("];\n");
+
+    fs::write(out_dir.join("embedded_assets.rs"), code).expect("failed to write embedded assets");
+}
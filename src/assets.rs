@@ -0,0 +1,248 @@
+/*
+Asset manager: Shares loaded GL objects between everything that uses them.
+
+Assets are handed out as reference counted `Handle`s, keyed by resource name. Asking for the same asset
+twice gives back the same GL object, and the object is deleted once the last handle to it is dropped.
+Names are normalised first (see `resource_key`), so `Textures/a.png` and `./textures/a.png` share one texture.
+*/
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::resources::Resources;
+use crate::resources::path::ResourcePath;
+use crate::render::program::Program;
+use crate::render::binary_cache::BinaryCache;
+use crate::render::texture::{Texture, TextureBuilder};
+use crate::render::variants::ProgramVariants;
+use crate::render::Error;
+use crate::obj::shape::Shape;
+use crate::obj::vertex::Vertex;
+
+// A shared reference to a loaded asset
+pub type Handle<T> = Rc<T>;
+
+/**
+Name -> asset lookup, which only keeps weak references so it never keeps an asset alive by itself
+*/
+pub struct Cache<T> {
+    entries: RefCell<HashMap<String, Weak<T>>>,
+}
+
+impl <T> Cache<T> {
+    pub fn new() -> Cache<T> {
+        Cache {
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Get the asset, if it's still loaded
+    pub fn get(&self, name: &str) -> Option<Handle<T>> {
+        self.entries.borrow().get(name).and_then(|weak| weak.upgrade())
+    }
+
+    // Get the asset, loading it if it isn't already loaded
+    pub fn get_or_load<E, F>(&self, name: &str, load: F) -> Result<Handle<T>, E>
+        where F: FnOnce() -> Result<T, E>
+    {
+        if let Some(handle) = self.get(name) {
+            return Ok(handle);
+        }
+
+        Ok(self.insert(name, load()?))
+    }
+
+    // Add an asset, replacing any existing asset of the same name (existing handles remain valid)
+    pub fn insert(&self, name: &str, asset: T) -> Handle<T> {
+        let handle = Rc::new(asset);
+        self.insert_handle(name, &handle);
+        handle
+    }
+
+    // Add an asset that's already shared
+    pub fn insert_handle(&self, name: &str, handle: &Handle<T>) {
+        self.entries.borrow_mut().insert(String::from(name), Rc::downgrade(handle));
+    }
+
+    // Forget about any assets that have been unloaded
+    pub fn purge(&self) {
+        self.entries.borrow_mut().retain(|_, weak| weak.strong_count() > 0);
+    }
+
+    // Names of the assets that are currently loaded
+    pub fn loaded_names(&self) -> Vec<String> {
+        self.entries.borrow().iter()
+            .filter(|(_, weak)| weak.strong_count() > 0)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+impl <T> Default for Cache<T> {
+    fn default() -> Self {
+        Cache::new()
+    }
+}
+
+// Lets us hold caches of different types together, and still purge them
+trait AnyCache {
+    fn purge(&self);
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl <T: 'static> AnyCache for Cache<T> {
+    fn purge(&self) {
+        Cache::purge(self);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/**
+Caches for each type of asset
+*/
+pub struct Assets {
+    textures: Cache<Texture>,
+    programs: Cache<Program>,
+    variants: Cache<ProgramVariants>,   // Keyed like `programs`
+    shapes: RefCell<HashMap<TypeId, Box<dyn AnyCache>>>,   // Vertex type -> Cache<Shape<Vertex type>>
+    binary_cache: Option<BinaryCache>,  // Where programs are loaded from and stored, if anywhere
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        Assets {
+            textures: Cache::new(),
+            programs: Cache::new(),
+            variants: Cache::new(),
+            shapes: RefCell::new(HashMap::new()),
+            binary_cache: None,
+        }
+    }
+
+    // Load programs from (and store them in) a binary cache
+    pub fn set_binary_cache(&mut self, binary_cache: Option<BinaryCache>) {
+        self.binary_cache = binary_cache;
+    }
+
+    pub fn binary_cache(&self) -> Option<&BinaryCache> {
+        self.binary_cache.as_ref()
+    }
+
+    // Get a texture, loading it from the resources if needed
+    pub fn texture(&self, res: &Resources, name: &str) -> Result<Handle<Texture>, Error> {
+        self.textures.get_or_load(&texture_key(name, &TextureBuilder::new()), || Texture::from_resource(res, name))
+    }
+
+    // Get a program, compiling it from the shader resources if needed
+    pub fn program<S: AsRef<str>>(&self, res: &Resources, shader_names: &[S]) -> Result<Handle<Program>, Error> {
+        self.programs.get_or_load(&program_key(shader_names), || {
+            Program::from_resources(res, shader_names, &[], self.binary_cache.as_ref())
+        })
+    }
+
+    /**
+    Get the variants of a program (see `render::variants`), creating them if needed.
+    Nothing is compiled until a variant is asked for.
+    */
+    pub fn program_variants<S: AsRef<str>>(&self, shader_names: &[S]) -> Handle<ProgramVariants> {
+        let key = program_key(shader_names);
+        if let Some(handle) = self.variants.get(&key) {
+            return handle;
+        }
+
+        let mut variants = ProgramVariants::new(shader_names);
+        variants.set_binary_cache(self.binary_cache.clone());
+        self.variants.insert(&key, variants)
+    }
+
+    /**
+    Get a shape, building it if needed.
+    `build` should return a shape that's already been `setup()`.
+    */
+    pub fn shape<T, F>(&self, name: &str, build: F) -> Handle<Shape<T>>
+        where T: Vertex + 'static, F: FnOnce() -> Shape<T>
+    {
+        if let Some(handle) = self.with_shape_cache(|cache: &Cache<Shape<T>>| cache.get(name)) {
+            return handle;
+        }
+
+        // Build outside of the cache borrow, in case building needs other shapes
+        let shape = build();
+        self.with_shape_cache(|cache| cache.insert(name, shape))
+    }
+
+    // Run a function against the shape cache for a given vertex type
+    fn with_shape_cache<T, R, F>(&self, f: F) -> R
+        where T: Vertex + 'static, F: FnOnce(&Cache<Shape<T>>) -> R
+    {
+        let mut shapes = self.shapes.borrow_mut();
+        let cache = shapes.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Cache::<Shape<T>>::new()))
+            .as_any()
+            .downcast_ref::<Cache<Shape<T>>>()
+            .unwrap();  // Safe to unwrap, as the entry is keyed by type
+
+        f(cache)
+    }
+
+    pub fn textures(&self) -> &Cache<Texture> {
+        &self.textures
+    }
+
+    pub fn programs(&self) -> &Cache<Program> {
+        &self.programs
+    }
+
+    pub fn variants(&self) -> &Cache<ProgramVariants> {
+        &self.variants
+    }
+
+    // Forget about any assets that have been unloaded
+    pub fn purge(&self) {
+        self.textures.purge();
+        self.programs.purge();
+        self.variants.purge();
+        for cache in self.shapes.borrow().values() {
+            cache.purge();
+        }
+    }
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Assets::new()
+    }
+}
+
+/**
+The name a resource's assets are cached under: its normalised, case insensitive name (see `ResourcePath::key`).
+Invalid names are used as they are, so they still fail to load rather than sharing another resource's asset.
+*/
+pub fn resource_key(name: &str) -> String {
+    ResourcePath::new(name)
+        .map(|path| String::from(path.key()))
+        .unwrap_or_else(|_| String::from(name))
+}
+
+/**
+The name textures are cached under: the image's name, plus the settings it's built with unless they're the defaults
+(so one image can be shared by textures with different filtering, mipmaps or colour spaces without mixing them up)
+*/
+pub fn texture_key(name: &str, builder: &TextureBuilder) -> String {
+    if *builder == TextureBuilder::new() {
+        resource_key(name)
+    } else {
+        format!("{}#{:?}", resource_key(name), builder)
+    }
+}
+
+// The name programs are cached under: the names of the shaders they're built from
+pub fn program_key<S: AsRef<str>>(shader_names: &[S]) -> String {
+    shader_names.iter().map(|name| resource_key(name.as_ref())).collect::<Vec<String>>().join("|")
+}
+
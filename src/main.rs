@@ -1,6 +1,7 @@
 use std::path::{Path};
-use std::ffi::{CString};
-use std::rc::Rc;
+use std::time::Duration;
+use std::sync::Arc;
+use std::collections::HashMap;
 
 extern crate sdl2;
 extern crate sdl2_sys;
@@ -10,9 +11,10 @@ pub mod resources;
 pub mod render;
 pub mod ffi_utils;
 pub mod obj;
+pub mod assets;
+pub mod manifest;
+pub mod shader_check;
 
-use crate::obj::vertex::{Vertex, VertexP, VertexPT, Coords3D};
-use crate::obj::shape::{Shape};
 
 // The SDL needs u32, but gl viewport needs i32
 // const SCREEN_WIDTH:u32 = 800;
@@ -33,27 +35,31 @@ macro_rules! SCREEN_WIDTH {
 }
 
 fn main() {
+    // `rusty-craft shader-check [pack...]` checks the shaders compile, rather than starting the game
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("shader-check") => shader_check::run(&args[1..]),
+        _ => run(),
+    };
+
+    // Report any failure cleanly, rather than panicking
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
     // Creating SDL instance
-    let sdl = sdl2::init().unwrap();
+    let sdl = sdl2::init()?;
     
     // Create a video subsystem
-    let video_subsystem = sdl.video().unwrap();
-
-    // Specify a OpenGL version and use "core" profile 
-    let gl_attr = video_subsystem.gl_attr();
-    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
-    gl_attr.set_context_version(3, 3);
+    let video_subsystem = sdl.video()?;
 
-    let window = video_subsystem
-        .window("Rusty-craft", SCREEN_WIDTH!(), SCREEN_HEIGHT!())
-        .opengl()   // We're using OpenGL
-        .resizable()
-        // .fullscreen() // Maybe later
-        .build()
-        .unwrap();
+    let window = create_window(&video_subsystem, "Rusty-craft", false)?;
 
     // OpenGL context
-    let _gl_context = window.gl_create_context().unwrap();  // TODO: Use this variable?
+    let _gl_context = window.gl_create_context()?;  // TODO: Use this variable?
 
     // Load the video subsystem
     let _gl = gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void); // TODO: Use this variable?
@@ -66,61 +72,100 @@ fn main() {
     }
 
     // Get a reference the event stream
-    let mut event_pump = sdl.event_pump().unwrap();
+    let mut event_pump = sdl.event_pump()?;
 
     // -- Load the shaders -- //
     // TODO: Load in another file, then import?
-    let resources = resources::Resources::from_relative_exe_path(Path::new("assets")).unwrap();
-    let vert_shader = render::shader::Shader::from_resource(&resources, "shaders/triangle.vert").unwrap();
-    let frag_shader = render::shader::Shader::from_resource(&resources, "shaders/triangle.frag").unwrap();
+    // Share the resources with the loading threads
+    let resources = Arc::new(load_resources()?);
     // -- -- //
 
-    // -- Load Texture -- //
-    // TODO: Maybe texture shouldn't automatically bind itself?
-    let texture = render::texture::Texture::from_resource(&resources, "textures/test_16.png").unwrap();
+    // -- Load assets in the background, with a loading screen -- //
+    let manifest = manifest::Manifest::from_resource(&resources, "manifest.toml")?;
 
-    // declare main sharder program
-    let shader_program = render::program::Program::from_shaders(
-        &[vert_shader, frag_shader]
-    ).unwrap();
-    
-    // Set the program as the main shader program
-    shader_program.set();
-
-    // New shape declaration: 
-    let mut square = Shape::from_vertices_and_triangle(
-        vec![
-            VertexPT {
-                position: (-0.5, -0.5, 0.0).into(),
-                texture_coords : (0.0,0.0).into(),
-            },
-            VertexPT {
-                position: (0.5, -0.5, 0.0).into(),
-                texture_coords : (1.0,0.0).into(),
-            },
-            VertexPT {
-                position: (0.5, 0.5, 0.0).into(),
-                texture_coords : (1.0,1.0).into(),
-            },
-            VertexPT {
-                position: (-0.5, 0.5, 0.0).into(),
-                texture_coords : (0.0,1.0).into(),
-            },
-        ], 
-        vec![
-            (0, 1, 2),    // First triangle
-            (2, 3, 0),    // Second triangle
-        ]
-    );
-
-    // Setup it's VAO and such
-    square.setup();
-
-    // Create global square reference:
-    let global_square = Rc::new(square);
-
-    // Get a world version of the square:
-    let mut in_world_square = obj::shape::Object::new(&global_square);
+    let mut loader = render::loader::Loader::new(Arc::clone(&resources), 4);
+    for (name, entry) in manifest.textures.iter() {
+        loader.load_texture_with(name, &entry.path, entry.builder());
+    }
+    // Linked programs are cached next to the executable, so they don't need compiling on the next start
+    let exe_path = std::env::current_exe()?;
+    let cache_dir = exe_path.parent().ok_or("Executable has no parent folder")?.join("cache").join("programs");
+    let binary_cache = render::binary_cache::BinaryCache::new(&cache_dir);
+    loader.set_binary_cache(binary_cache.clone());
+    for (name, entry) in manifest.programs.iter() {
+        loader.load_program(name, &entry.shaders);
+    }
+
+    let loaded = match loading_screen(&window, &mut event_pump, &mut loader) {
+        Some(loaded) => loaded,
+        None => return Ok(()),  // Closed while loading
+    };
+
+    // Sort out what's been loaded
+    let mut textures = HashMap::new();
+    let mut programs = HashMap::new();
+    for item in loaded {
+        match item {
+            render::loader::Loaded::Texture { name, result, animation } => { textures.insert(name, (result?, animation)); },
+            render::loader::Loaded::Program { name, result } => { programs.insert(name, result?); },
+        }
+    }
+    // -- -- //
+
+    // Shared assets
+    let mut assets = assets::Assets::new();
+    assets.set_binary_cache(binary_cache.clone());
+
+    // -- Textures and programs, which hot reload when their files change -- //
+    // Textures with an animation sidecar were loaded as their first frame, and play in the main loop
+    let mut reloadable_textures = Vec::new();
+    for (name, entry) in manifest.textures.iter() {
+        let (texture, animation) = textures.remove(name).ok_or_else(|| format!("Texture {} was not loaded", name))?;
+        let texture = render::reload::ReloadableTexture::from_texture(&entry.path, entry.builder(), texture, animation);
+        assets.textures().insert_handle(&assets::texture_key(&entry.path, texture.builder()), &texture.handle());
+        reloadable_textures.push(texture);
+    }
+
+    // Materials each use a variant of a program (see `render::variants`): the loader built the one without defines
+    let mut program_variants = Vec::new();
+    for (name, entry) in manifest.programs.iter() {
+        let program = programs.remove(name).ok_or_else(|| format!("Program {} was not loaded", name))?;
+        let variants = assets.program_variants(&entry.shaders);
+        variants.insert::<&str>(&[], program);
+        program_variants.push((name.clone(), variants));
+    }
+
+    // Materials pick up the programs and textures loaded above from the asset caches
+    let mut materials = HashMap::new();
+    for name in manifest.materials.keys() {
+        materials.insert(name.clone(), manifest.load_material(&resources, &assets, name)?);
+    }
+
+    // -- What to draw -- //
+    let mut objects = Vec::new();
+    for entry in manifest.objects.iter() {
+        let object = manifest.load_object(&assets, entry)?;
+        let material = materials.get(&entry.material).ok_or_else(|| format!("No material named {}", entry.material))?;
+        object.check_vertex_layout(material.program())?;
+        objects.push((object, entry.material.clone()));
+    }
+
+    // Watch the loaded files, so edits are picked up without restarting
+    let mut watcher = resources::watcher::Watcher::new(Duration::from_millis(500));
+    for (_, variants) in program_variants.iter() {
+        for name in variants.dependencies() {
+            watcher.watch(&resources, &name);
+        }
+    }
+    for texture in reloadable_textures.iter() {
+        watcher.watch(&resources, texture.resource_name());
+        // Adding a sidecar makes a texture animated
+        watcher.watch(&resources, &texture.sidecar_name());
+    }
+
+    // Per-frame data shared by every program
+    let mut frame_data = render::frame::FrameData::default();
+    let frame_buffer = render::uniform_buffer::UniformBuffer::new(render::frame::FrameData::BINDING, &frame_data);
 
     // Loop state variables
     let mut last_tick = unsafe{sdl2_sys::SDL_GetTicks()};
@@ -133,19 +178,66 @@ fn main() {
         let fps : f32 = 1000.0 / ( tick_diff as f32);
         // println!("fsp {}", fps);    // TODO: Write to corner of screen?
         last_tick = cur_tick;
+
+        // Hot reload anything that's changed on disk
+        for changed in watcher.poll(&resources) {
+            for (program_name, variants) in program_variants.iter().filter(|(_, variants)| variants.depends_on(&changed)) {
+                match variants.reload(&resources) {
+                    Ok(()) => {
+                        println!("Reloaded shader program ({})", changed);
+                        // Each material gets the new build of its own variant, or keeps its current one if that fails
+                        for (material_name, entry) in manifest.materials.iter().filter(|(_, entry)| &entry.program == program_name) {
+                            match (materials.get_mut(material_name), variants.get(&resources, &entry.defines)) {
+                                (Some(material), Ok(program)) => material.set_program(program),
+                                (Some(_), Err(e)) => eprintln!("Failed to reload material {}, keeping the old program:\n{}", material_name, e),
+                                (None, _) => {},
+                            }
+                        }
+                        for (object, material_name) in objects.iter() {
+                            if let Err(e) = object.check_vertex_layout(materials[material_name].program()) {
+                                eprintln!("Warning: {}", e);
+                            }
+                        }
+                        // The shaders may include different files now
+                        for name in variants.dependencies() {
+                            watcher.watch(&resources, &name);
+                        }
+                    },
+                    Err(e) => eprintln!("Failed to reload shader program ({}), keeping the old one:\n{}", changed, e),
+                }
+            }
+            for texture in reloadable_textures.iter_mut().filter(|texture| texture.depends_on(&changed)) {
+                let old_texture = texture.handle();
+                match texture.reload(&resources) {
+                    Ok(()) => {
+                        println!("Reloaded texture ({})", changed);
+                        // So anything loaded later shares the new texture, rather than loading the file again
+                        assets.textures().insert_handle(&assets::texture_key(texture.resource_name(), texture.builder()), &texture.handle());
+                        for material in materials.values_mut() {
+                            material.replace_texture(&old_texture, &texture.handle());
+                        }
+                    },
+                    Err(e) => eprintln!("Failed to reload texture ({}), keeping the old one:\n{}", changed, e),
+                }
+            }
+        }
         
-        // Modify a `uniform` variable to affect triangle colour
+        // Play animated textures
+        for texture in reloadable_textures.iter_mut() {
+            if let Err(e) = texture.update_animation(tick_diff) {
+                eprintln!("Stopping animation: {}", e);
+            }
+        }
+
+        // Update the data shared by every program
+        frame_data.time = (cur_tick as f32) / 1000.0;
+        frame_buffer.update(&frame_data);
+
+        // Angle to rotate the objects by
         const LOOP_TIME :u32 = 10000;
         u_colour_angle = (u_colour_angle + tick_diff) % LOOP_TIME;
         // Normalise around 10000 -> 2*pi.
         let u_colour_angle_rad = ((u_colour_angle as f32) * 2.0 * std::f32::consts::PI)/ (LOOP_TIME as f32);
-        unsafe{
-            let radius_uniform_location = gl::GetUniformLocation(shader_program.id(), CString::new("timed_colour").unwrap().as_ptr() );
-            gl::Uniform1f(
-                radius_uniform_location, //GLint, 
-                u_colour_angle_rad as gl::types::GLfloat
-            );
-        }
 
 
         // Handle events:
@@ -169,13 +261,95 @@ fn main() {
         // TODO: render something
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT);
-            in_world_square.rotation.x = u_colour_angle_rad;
-            in_world_square.rotation.y = u_colour_angle_rad + 1.6;
+            // Draw our objects:
+            for (object, material_name) in objects.iter_mut() {
+                let rotation = object.rotation_mut();
+                rotation.x = u_colour_angle_rad;
+                rotation.y = u_colour_angle_rad + 1.6;
 
-            // Draw our shape:
-            in_world_square.draw(shader_program.id());
+                object.draw(&materials[material_name]);
+            }
         }
         
         window.gl_swap_window();
     }
+
+    Ok(())
+}
+
+// Create the game's window, with an OpenGL 3.3 core context available. A hidden window is only used for its context.
+fn create_window(
+    video_subsystem: &sdl2::VideoSubsystem,
+    title: &str,
+    hidden: bool
+) -> Result<sdl2::video::Window, Box<dyn std::error::Error>> {
+    // Specify a OpenGL version and use "core" profile 
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(3, 3);
+
+    let mut builder = video_subsystem.window(title, SCREEN_WIDTH!(), SCREEN_HEIGHT!());
+    builder
+        .opengl()   // We're using OpenGL
+        .resizable();
+        // .fullscreen() // Maybe later
+    if hidden {
+        builder.hidden();
+    }
+
+    Ok(builder.build()?)
+}
+
+// The base assets, with any resource packs on top
+fn load_resources() -> Result<resources::Resources, Box<dyn std::error::Error>> {
+    let mut resources = resources::Resources::from_relative_exe_path(Path::new("assets"))?;
+    // In debug builds, read the repository's assets directly, so hot reloading picks up edits to them
+    // (rather than the copies the build script puts next to the executable)
+    if cfg!(debug_assertions) {
+        let source_assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        if source_assets.is_dir() {
+            resources.add_pack("source", &source_assets)?;
+        }
+    }
+    // Any folders in `resourcepacks` override the base assets
+    resources.add_packs_from_relative_exe_path(Path::new("resourcepacks"))?;
+
+    Ok(resources)
+}
+
+// Show a progress bar until everything in the loader has been loaded. Returns `None` if the game is closed.
+fn loading_screen(
+    window: &sdl2::video::Window,
+    event_pump: &mut sdl2::EventPump,
+    loader: &mut render::loader::Loader
+) -> Option<Vec<render::loader::Loaded>> {
+    let mut loaded = Vec::new();
+
+    while !loader.is_done() {
+        for event in event_pump.poll_iter() {
+            if let sdl2::event::Event::Quit {..} = event {
+                return None;
+            }
+        }
+
+        // Spend a bounded amount of each frame creating GL objects, so the window stays responsive
+        loaded.extend(loader.upload(Duration::from_millis(8)));
+
+        // Draw a progress bar, by clearing a rectangle of the screen
+        let (completed, requested) = loader.progress();
+        let bar_width = (SCREEN_WIDTH!() - 100) * (completed as i32) / (requested.max(1) as i32);
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(50, SCREEN_HEIGHT!() / 2 - 10, bar_width, 20);
+            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::ClearColor(0.5, 0.5, 0.7, 1.0); // Restore the usual 'clear' colour
+        }
+
+        window.gl_swap_window();
+    }
+
+    Some(loaded)
 }
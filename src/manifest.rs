@@ -0,0 +1,286 @@
+/*
+The asset manifest (`assets/manifest.toml`) describes the content the game loads: shader programs and the
+stages they're linked from, textures and how they're sampled, materials combining them, and meshes. Changing content only needs
+the manifest to be edited, not the code.
+
+eg/
+```toml
+[programs.triangle]
+shaders = ["shaders/triangle.vert", "shaders/triangle.frag"]
+
+[textures.test]
+path = "textures/test_16.png"
+filter = "nearest"          # nearest | linear
+wrap = "repeat"             # repeat | mirrored_repeat | clamp_to_edge
+mipmaps = "linear"          # How to blend between mip levels: nearest | linear (no mipmaps if left out)
+anisotropy = 8.0            # Anisotropic filtering, if the driver supports it (1 is off)
+lod_bias = 0.0              # Added to the mip level: positive is blurrier
+colour_space = "srgb"       # linear | srgb
+
+[materials.test]
+program = "triangle"
+defines = ["ALPHA_CUTOUT"]              # Shader variant to use (see `render::variants`)
+textures = { ourTexture = "test" }      # Sampler uniform -> texture
+uniforms = { tint = [1.0, 0.5, 0.5] }   # Default uniform values
+
+[meshes.square]
+vertex = "VertexPT"                             # Vertex type (see `obj::vertex`), defaults to VertexPT
+vertices = [[-0.5, -0.5, 0.0, 0.0, 0.0], ...]   # Values for `Vertex::from_vec`
+triangles = [[0, 1, 2], ...]
+
+[[objects]]             # What the game draws: a mesh, with a material
+mesh = "square"
+material = "test"
+```
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use serde::Deserialize;
+
+use crate::resources::{self, Resources};
+use crate::render;
+use crate::render::texture::{Texture, TextureBuilder, Filter, Wrap, ColourSpace};
+use crate::render::material::Material;
+use crate::render::uniform::UniformValue;
+use crate::assets::{self, Assets};
+use crate::obj::shape::{Shape, Object, Drawable};
+use crate::obj::vertex::{self, Vertex, VertexP, VertexPC, VertexPT, VertexPCT, VertexPTL};
+
+#[derive(Debug)]
+pub enum Error {
+    Resource(resources::Error),
+    Parse(toml::de::Error),
+    Render(render::Error),
+    Unknown { kind: &'static str, name: String },
+    InvalidMesh { name: String, reason: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Resource(e) => write!(f, "Failed to load manifest: {}", e),
+            Error::Parse(e) => write!(f, "Failed to parse manifest: {}", e),
+            Error::Render(e) => write!(f, "{}", e),
+            Error::Unknown { kind, name } => write!(f, "No {} named {} in the manifest", kind, name),
+            Error::InvalidMesh { name, reason } => write!(f, "Invalid mesh {}: {}", name, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Resource(e) => Some(e),
+            Error::Parse(e) => Some(e),
+            Error::Render(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<render::Error> for Error {
+    fn from(other: render::Error) -> Self {
+        Error::Render(other)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgramEntry {
+    pub shaders: Vec<String>,   // Shader resource names: one per stage, or a multi-stage `.glsl`
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextureEntry {
+    pub path: String,   // Image resource name
+    #[serde(default = "default_filter")]
+    pub filter: Filter,
+    #[serde(default = "default_wrap")]
+    pub wrap: Wrap,
+    #[serde(default)]
+    pub mipmaps: Option<Filter>,
+    #[serde(default = "default_anisotropy")]
+    pub anisotropy: f32,
+    #[serde(default)]
+    pub lod_bias: f32,
+    #[serde(default)]
+    pub colour_space: ColourSpace,
+}
+
+fn default_filter() -> Filter {
+    Filter::Nearest
+}
+
+fn default_wrap() -> Wrap {
+    Wrap::Repeat
+}
+
+fn default_anisotropy() -> f32 {
+    1.0
+}
+
+impl TextureEntry {
+    // The settings to build this entry's texture with
+    pub fn builder(&self) -> TextureBuilder {
+        TextureBuilder::new()
+            .with_filter(self.filter)
+            .with_wrap(self.wrap)
+            .with_mipmaps(self.mipmaps)
+            .with_anisotropy(self.anisotropy)
+            .with_lod_bias(self.lod_bias)
+            .with_colour_space(self.colour_space)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaterialEntry {
+    pub program: String,    // Name of a program in the manifest
+    #[serde(default)]
+    pub defines: Vec<String>,   // Defines to compile the program with
+    #[serde(default)]
+    pub textures: BTreeMap<String, String>,     // Sampler uniform -> name of a texture in the manifest
+    #[serde(default)]
+    pub uniforms: BTreeMap<String, UniformValue>,
+}
+
+// The vertex types a mesh can be built from
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum VertexType {
+    VertexP,
+    VertexPC,
+    #[default]
+    VertexPT,
+    VertexPCT,
+    VertexPTL,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeshEntry {
+    #[serde(default)]
+    pub vertex: VertexType,
+    pub vertices: Vec<Vec<f32>>,
+    pub triangles: Vec<(u32, u32, u32)>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObjectEntry {
+    pub mesh: String,       // Name of a mesh in the manifest
+    pub material: String,   // Name of a material in the manifest
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub programs: HashMap<String, ProgramEntry>,
+    #[serde(default)]
+    pub textures: HashMap<String, TextureEntry>,
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialEntry>,
+    #[serde(default)]
+    pub meshes: HashMap<String, MeshEntry>,
+    #[serde(default)]
+    pub objects: Vec<ObjectEntry>,
+}
+
+impl Manifest {
+    pub fn from_resource(res: &Resources, name: &str) -> Result<Manifest, Error> {
+        let source = res.load_bytes(name).map_err(Error::Resource)?;
+        toml::from_slice(&source).map_err(Error::Parse)
+    }
+
+    pub fn program(&self, name: &str) -> Result<&ProgramEntry, Error> {
+        self.programs.get(name).ok_or_else(|| unknown("program", name))
+    }
+
+    pub fn texture(&self, name: &str) -> Result<&TextureEntry, Error> {
+        self.textures.get(name).ok_or_else(|| unknown("texture", name))
+    }
+
+    pub fn material(&self, name: &str) -> Result<&MaterialEntry, Error> {
+        self.materials.get(name).ok_or_else(|| unknown("material", name))
+    }
+
+    pub fn mesh(&self, name: &str) -> Result<&MeshEntry, Error> {
+        self.meshes.get(name).ok_or_else(|| unknown("mesh", name))
+    }
+
+    // -- Builders -- //
+    // Load a texture, with its sampler settings applied
+    pub fn load_texture(&self, res: &Resources, name: &str) -> Result<Texture, Error> {
+        let entry = self.texture(name)?;
+
+        Ok(entry.builder().build_from_resource(res, &entry.path)?)
+    }
+
+    /**
+    Build a material. Its program variant and textures are shared through `assets`, so they're only loaded if nothing
+    else has loaded them already. (After the program's variants are reloaded, `get` the material's define set again)
+    */
+    pub fn load_material(&self, res: &Resources, assets: &Assets, name: &str) -> Result<Material, Error> {
+        let entry = self.material(name)?;
+
+        let program = assets.program_variants(&self.program(&entry.program)?.shaders).get(res, &entry.defines)?;
+        let mut material = Material::new(program);
+
+        // Textures are given units in order of their sampler's name
+        for (uniform, texture_name) in entry.textures.iter() {
+            let texture_entry = self.texture(texture_name)?;
+            let key = assets::texture_key(&texture_entry.path, &texture_entry.builder());
+            let texture = assets.textures().get_or_load(&key, || self.load_texture(res, texture_name))?;
+            material.set_texture(uniform, texture);
+        }
+
+        for (uniform, value) in entry.uniforms.iter() {
+            material.set_uniform(uniform, *value);
+        }
+
+        Ok(material)
+    }
+
+    // Build a mesh as a shape of the given vertex type. Note: The shape still needs to be `setup()`.
+    pub fn load_shape<T: Vertex>(&self, name: &str) -> Result<Shape<T>, Error> {
+        let entry = self.mesh(name)?;
+        let verts = entry.vertices.iter()
+            .map(|values| T::from_vec(values.clone()))
+            .collect::<Result<Vec<T>, vertex::Error>>()
+            .map_err(|e| Error::InvalidMesh { name: String::from(name), reason: format!("{:?}", e) })?;
+
+        // Make sure the triangles don't reference vertices that don't exist
+        let vertex_count = verts.len() as u32;
+        if let Some(triangle) = entry.triangles.iter().find(|t| t.0 >= vertex_count || t.1 >= vertex_count || t.2 >= vertex_count) {
+            let reason = format!("Triangle {:?} is out of range of the {} vertices", triangle, vertex_count);
+            return Err(Error::InvalidMesh { name: String::from(name), reason });
+        }
+
+        Ok(Shape::from_vertices_and_triangle(verts, entry.triangles.clone()))
+    }
+
+    /**
+    Build an object, as whichever vertex type its mesh uses. Its shape is shared through `assets`,
+    so each mesh is only set up once.
+    */
+    pub fn load_object(&self, assets: &Assets, entry: &ObjectEntry) -> Result<Box<dyn Drawable>, Error> {
+        Ok(match self.mesh(&entry.mesh)?.vertex {
+            VertexType::VertexP => Box::new(self.load_object_of::<VertexP>(assets, &entry.mesh)?),
+            VertexType::VertexPC => Box::new(self.load_object_of::<VertexPC>(assets, &entry.mesh)?),
+            VertexType::VertexPT => Box::new(self.load_object_of::<VertexPT>(assets, &entry.mesh)?),
+            VertexType::VertexPCT => Box::new(self.load_object_of::<VertexPCT>(assets, &entry.mesh)?),
+            VertexType::VertexPTL => Box::new(self.load_object_of::<VertexPTL>(assets, &entry.mesh)?),
+        })
+    }
+
+    fn load_object_of<T: Vertex + 'static>(&self, assets: &Assets, mesh: &str) -> Result<Object<T>, Error> {
+        let shape = self.load_shape::<T>(mesh)?;
+        let shape = assets.shape(mesh, move || {
+            let mut shape = shape;
+            shape.setup();
+            shape
+        });
+
+        Ok(Object::new(&shape))
+    }
+}
+
+fn unknown(kind: &'static str, name: &str) -> Error {
+    Error::Unknown { kind, name: String::from(name) }
+}
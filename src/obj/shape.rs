@@ -103,23 +103,34 @@ impl <T:Vertex> Shape<T> {
     }
 }
 
-// TODO: Implement Drop trait:
-// impl Drop for Shape<T> {
-//     /**
-//     When the Shape is dropped, we should delete associated VAOs, VBOs and EBOs
-//     */
-//     fn drop(&mut self) {
-//         unsafe {
-//         }
-//     }
-// }
+impl <T:Vertex> Drop for Shape<T> {
+    /**
+    When the Shape is dropped, we should delete associated VAOs, VBOs and EBOs
+    */
+    fn drop(&mut self) {
+        // IDs are only non-zero once `setup()` has created them
+        unsafe {
+            if self.vao != 0 {
+                gl::DeleteVertexArrays(1, &self.vao);
+            }
+            if self.vbo != 0 {
+                gl::DeleteBuffers(1, &self.vbo);
+            }
+            if self.ebo != 0 {
+                gl::DeleteBuffers(1, &self.ebo);
+            }
+        }
+    }
+}
 
 /**
  * An Object is a specific instance of a given shape: It will contain a reference to the original shape, 
  * but will have it's own local->world space transformations.
  */
 use std::rc::Rc;
-use std::ffi::{CString};
+use crate::render::material::Material;
+use crate::render::program::Program;
+use crate::render;
 
 pub struct Object<T:Vertex> {
     shape: Rc<Shape<T>>,
@@ -140,33 +151,42 @@ impl <T:Vertex> Object<T> {
         }
     }
 
-    pub fn draw(&self, shader_program_id : gl::types::GLuint) {
+    pub fn draw(&self, material: &Material) {
+        material.apply();
+
         // Setup up the transformations for openGL
-        unsafe {
-            let translation_location = gl::GetUniformLocation(shader_program_id, CString::new("translation").unwrap().as_ptr());
-            gl::Uniform3f(
-                translation_location,
-                self.translation.x,
-                self.translation.y,
-                self.translation.z
-            );
-            let rotation_location = gl::GetUniformLocation(shader_program_id, CString::new("rotation").unwrap().as_ptr());
-            gl::Uniform3f(
-                rotation_location,
-                self.rotation.x,
-                self.rotation.y,
-                self.rotation.z
-            );
-            let scale_location = gl::GetUniformLocation(shader_program_id, CString::new("scale").unwrap().as_ptr());
-            gl::Uniform3f(
-                scale_location,
-                self.scale.x,
-                self.scale.y,
-                self.scale.z
-            );
-        }
+        let program = material.program();
+        program.set_uniform("translation", self.translation);
+        program.set_uniform("rotation", self.rotation);
+        program.set_uniform("scale", self.scale);
 
         // draw
         self.shape.draw();
     }
+}
+
+/**
+ * Lets objects of different vertex types be kept together, eg/ when the type comes from the manifest.
+ */
+pub trait Drawable {
+    fn draw(&self, material: &Material);
+
+    // Check a program can draw the object's vertices
+    fn check_vertex_layout(&self, program: &Program) -> Result<(), render::Error>;
+
+    fn rotation_mut(&mut self) -> &mut Coords3D;
+}
+
+impl <T:Vertex> Drawable for Object<T> {
+    fn draw(&self, material: &Material) {
+        Object::draw(self, material);
+    }
+
+    fn check_vertex_layout(&self, program: &Program) -> Result<(), render::Error> {
+        program.check_vertex_layout::<T>()
+    }
+
+    fn rotation_mut(&mut self) -> &mut Coords3D {
+        &mut self.rotation
+    }
 }
\ No newline at end of file
@@ -110,11 +110,50 @@ impl From<(f32, f32, f32)> for Colour {
 }
 
 // -- Vertex -- //
+/**
+One attribute of a vertex: a run of `f32`s, read by the vertex shader input with `layout (location = <location>)`
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub name: &'static str,     // For error reporting
+    pub location: gl::types::GLuint,
+    pub components: gl::types::GLint,   // Number of floats
+    pub offset: usize,  // Offset in bytes from the start of the vertex
+}
+
 pub trait Vertex {
     fn to_vec(&self) -> Vec<f32>;
     fn from_vec(v: Vec<f32>) -> Result<Self, Error> where Self : Sized;    // TODO: Do I want a Result type? Should I "Size" the class
 
-    fn configure_vao(vao_id: gl::types::GLuint) -> gl::types::GLuint;
+    // The layout of the vertex. This is used to configure VAOs, and to check shaders expect the same layout.
+    fn attributes() -> &'static [Attribute];
+
+    // Takes in a VAO id, configure it (from the `attributes()`) and returns it.
+    fn configure_vao(vao_id: gl::types::GLuint) -> gl::types::GLuint where Self: Sized {
+        unsafe {
+            gl::BindVertexArray(vao_id);
+
+            // Vertices are tightly packed, so the stride is the whole vertex
+            let stride = std::mem::size_of::<Self>() as gl::types::GLint;
+            for attribute in Self::attributes() {
+                gl::EnableVertexAttribArray(attribute.location); // this is "layout (location = N)" in vertex shader
+                gl::VertexAttribPointer(
+                    attribute.location, // index of the generic vertex attribute
+                    attribute.components, // the number of components per generic vertex attribute
+                    gl::FLOAT, // data type
+                    gl::FALSE, // disable normalization (int-to-float conversion)
+                    stride, // stride (byte offset between consecutive attributes)
+                    attribute.offset as *const gl::types::GLvoid // offset of the first component
+                );
+            }
+
+            // Unbind the VAO
+            gl::BindVertexArray(0);
+        }
+
+        // Return the id
+        vao_id
+    }
 }
 
 
@@ -166,30 +205,13 @@ impl Vertex for VertexP {
         }
     }
 
-    // Takes in a VAO id, configure it and returns it.
-    fn configure_vao(vao_id: gl::types::GLuint) -> gl::types::GLuint {
-        unsafe {
-            gl::BindVertexArray(vao_id);
-    
-            // -- Set Attributes -- //
-            let stride = std::mem::size_of::<Coords3D>() as gl::types::GLint;
-            // Position info:
-            gl::EnableVertexAttribArray(0); // this is "layout (location = 0)" in vertex shader
-            gl::VertexAttribPointer(
-                0, // index of the generic vertex attribute ("layout (location = 0)")
-                3, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                std::ptr::null() // offset of the first component
-            );
+    // The vertex's attributes, as they're laid out in memory
+    fn attributes() -> &'static [Attribute] {
+        const LAYOUT: &[Attribute] = &[
+            Attribute { name: "position", location: 0, components: 3, offset: 0 },
+        ];
 
-            // Unbind the VAO
-            gl::BindVertexArray(0);
-        }
-
-        // Return the id
-        vao_id
+        LAYOUT
     }
 }
 
@@ -239,40 +261,14 @@ impl Vertex for VertexPC {
         )
     }
 
-    // Takes in a VAO id, configure it and returns it.
-    fn configure_vao(vao_id: gl::types::GLuint) -> gl::types::GLuint {
-        unsafe {
-            gl::BindVertexArray(vao_id);
-            
-            // -- Set Attributes -- //
-            let stride = (std::mem::size_of::<Coords3D>() + std::mem::size_of::<Colour>()) as gl::types::GLint;
-            // Position info:
-            gl::EnableVertexAttribArray(0); // this is "layout (location = 0)" in vertex shader
-            gl::VertexAttribPointer(
-                0, // index of the generic vertex attribute ("layout (location = 0)")
-                3, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                std::ptr::null() // offset of the first component
-            );
-            // Base colour info:
-            gl::EnableVertexAttribArray(1); // this is "layout (location = 1)" in vertex shader
-            gl::VertexAttribPointer(
-                1, // index of the generic vertex attribute ("layout (location = 1)")
-                4, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                (std::mem::size_of::<Coords3D>()) as *const gl::types::GLvoid // offset of the first component
-            );
+    // The vertex's attributes, as they're laid out in memory
+    fn attributes() -> &'static [Attribute] {
+        const LAYOUT: &[Attribute] = &[
+            Attribute { name: "position", location: 0, components: 3, offset: 0 },
+            Attribute { name: "colour", location: 1, components: 4, offset: std::mem::size_of::<Coords3D>() },
+        ];
 
-            // Unbind the VAO
-            gl::BindVertexArray(0);
-        }
-            
-        // Return the id
-        vao_id
+        LAYOUT
     }
 }
 
@@ -292,6 +288,14 @@ impl VertexPT {
             texture_coords: self.texture_coords,
         }
     }
+
+    pub fn add_layer(self, layer: f32) -> VertexPTL {
+        VertexPTL {
+            position: self.position,
+            texture_coords: self.texture_coords,
+            layer,
+        }
+    }
 }
 
 impl Vertex for VertexPT {
@@ -320,41 +324,14 @@ impl Vertex for VertexPT {
         )
     }
 
-    // Takes in a VAO id, configure it and returns it.
-    fn configure_vao(vao_id: gl::types::GLuint) -> gl::types::GLuint {
-        unsafe {
-            gl::BindVertexArray(vao_id);
-            
-            // -- Set Attributes -- //
-            let stride = (std::mem::size_of::<Coords3D>() + std::mem::size_of::<Coords2D>()) as gl::types::GLint;
-
-            // Position info:
-            gl::EnableVertexAttribArray(0); // this is "layout (location = 0)" in vertex shader
-            gl::VertexAttribPointer(
-                0, // index of the generic vertex attribute ("layout (location = 0)")
-                3, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                std::ptr::null() // offset of the first component
-            );
-            // Texture info:
-            gl::EnableVertexAttribArray(1); // this is "layout (location = 1)" in vertex shader
-            gl::VertexAttribPointer(
-                1, // index of the generic vertex attribute ("layout (location = 1)")
-                2, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                (std::mem::size_of::<Coords3D>()) as *const gl::types::GLvoid // offset of the first component
-            );
-
-            // Unbind the VAO
-            gl::BindVertexArray(0);
-        }
+    // The vertex's attributes, as they're laid out in memory
+    fn attributes() -> &'static [Attribute] {
+        const LAYOUT: &[Attribute] = &[
+            Attribute { name: "position", location: 0, components: 3, offset: 0 },
+            Attribute { name: "texture_coords", location: 1, components: 2, offset: std::mem::size_of::<Coords3D>() },
+        ];
 
-        // Return the id
-        vao_id
+        LAYOUT
     }
 }
 
@@ -384,8 +361,8 @@ impl Vertex for VertexPCT {
     }
 
     fn from_vec(v: Vec<f32>) -> Result<VertexPCT, Error> {
-        // Need atleast 7 values
-        if v.len() < 5 { return Err(Error::InvalidVectorLength); }
+        // Need atleast 9 values
+        if v.len() < 9 { return Err(Error::InvalidVectorLength); }
 
         let position = Coords3D::from_vec(vec![v[0], v[1], v[2]])?;
         let colour = Colour::from_vec(vec![v[3],v[4],v[5],v[6]])?;
@@ -399,56 +376,76 @@ impl Vertex for VertexPCT {
         )
     }
 
-    // Takes in a VAO id, configure it and returns it.
-    fn configure_vao(vao_id: gl::types::GLuint) -> gl::types::GLuint {
-        unsafe {
-            // Bind the VAO to context
-            gl::BindVertexArray(vao_id);
-            
-            // -- Set Attributes -- //
-            let stride = (
-                std::mem::size_of::<Coords3D>() 
-                + std::mem::size_of::<Colour>()
-                + std::mem::size_of::<Coords2D>()
-            ) as gl::types::GLint;
-            // Position info:
-            gl::EnableVertexAttribArray(0); // this is "layout (location = 0)" in vertex shader
-            gl::VertexAttribPointer(
-                0, // index of the generic vertex attribute ("layout (location = 0)")
-                3, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                std::ptr::null() // offset of the first component
-            );
-
-            // Base colour info:
-            gl::EnableVertexAttribArray(1); // this is "layout (location = 1)" in vertex shader
-            gl::VertexAttribPointer(
-                1, // index of the generic vertex attribute ("layout (location = 1)")
-                4, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                (std::mem::size_of::<Coords3D>()) as *const gl::types::GLvoid // offset of the first component
-            );
-
-            // Texture info:
-            gl::EnableVertexAttribArray(2); // this is "layout (location = 2)" in vertex shader
-            gl::VertexAttribPointer(
-                2, // index of the generic vertex attribute ("layout (location = 2)")
-                2, // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // disable normalization (int-to-float conversion)
-                stride, // stride (byte offset between consecutive attributes)
-                (std::mem::size_of::<Coords3D>() + std::mem::size_of::<Colour>()) as *const gl::types::GLvoid // offset of the first component
-            );
+    // The vertex's attributes, as they're laid out in memory
+    fn attributes() -> &'static [Attribute] {
+        const LAYOUT: &[Attribute] = &[
+            Attribute { name: "position", location: 0, components: 3, offset: 0 },
+            Attribute { name: "colour", location: 1, components: 4, offset: std::mem::size_of::<Coords3D>() },
+            Attribute {
+                name: "texture_coords",
+                location: 2,
+                components: 2,
+                offset: std::mem::size_of::<Coords3D>() + std::mem::size_of::<Colour>(),
+            },
+        ];
 
-            // Unbind the VAO
-            gl::BindVertexArray(0);
-        }
-   
-        // Return the id
-        vao_id
+        LAYOUT
     }
-}
\ No newline at end of file
+}
+
+/**
+A vertex textured from a `TextureArray`: `layer` is the index of the array layer to read.
+It's a float (like every other attribute), but should always hold a whole number.
+*/
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct VertexPTL {
+    pub position : Coords3D,
+    pub texture_coords: Coords2D,
+    pub layer: f32,
+}
+
+impl Vertex for VertexPTL {
+    // -- Getter -- //
+    fn to_vec(&self) -> Vec<f32> {
+        vec![
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.texture_coords.x,
+            self.texture_coords.y,
+            self.layer,
+        ]
+    }
+
+    fn from_vec(v: Vec<f32>) -> Result<VertexPTL, Error> {
+        // Need atleast 6 values
+        if v.len() < 6 { return Err(Error::InvalidVectorLength); }
+
+        let position = Coords3D::from_vec(vec![v[0], v[1], v[2]])?;
+        let texture_coords = Coords2D::from_vec(vec![v[3],v[4]])?;
+        Ok(
+            VertexPTL {
+                position,
+                texture_coords,
+                layer: v[5],
+            }
+        )
+    }
+
+    // The vertex's attributes, as they're laid out in memory
+    fn attributes() -> &'static [Attribute] {
+        const LAYOUT: &[Attribute] = &[
+            Attribute { name: "position", location: 0, components: 3, offset: 0 },
+            Attribute { name: "texture_coords", location: 1, components: 2, offset: std::mem::size_of::<Coords3D>() },
+            Attribute {
+                name: "layer",
+                location: 2,
+                components: 1,
+                offset: std::mem::size_of::<Coords3D>() + std::mem::size_of::<Coords2D>(),
+            },
+        ];
+
+        LAYOUT
+    }
+}
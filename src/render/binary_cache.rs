@@ -0,0 +1,164 @@
+/*
+An on-disk cache of linked programs, so they don't need compiling again on the next start.
+
+Programs are stored with `glGetProgramBinary`, keyed by a hash of their preprocessed sources and the driver
+they were built by (binaries are only valid for the exact driver that made them). The cache is best effort:
+anything that can't be read, written or loaded is just compiled instead.
+*/
+
+use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::render::shader::Stage;
+
+// Start of every cache file. Bump the version if the file layout (or the key) changes.
+const MAGIC: &[u8; 4] = b"RCPB";
+const VERSION: u32 = 1;
+
+#[derive(Clone)]
+pub struct BinaryCache {
+    dir: PathBuf,
+    driver: String,     // Vendor, renderer and version of the GL driver
+}
+
+impl BinaryCache {
+    /**
+    Use a folder (created when needed) as a cache. Must be called with a GL context current.
+    Returns `None` if the driver can't save program binaries.
+    */
+    pub fn new(dir: &Path) -> Option<BinaryCache> {
+        if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+            return None;
+        }
+
+        let mut format_count: gl::types::GLint = 0;
+        unsafe { gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut format_count); }
+        if format_count <= 0 {
+            return None;
+        }
+
+        let driver = [gl::VENDOR, gl::RENDERER, gl::VERSION].iter()
+            .map(|&name| gl_string(name))
+            .collect::<Vec<String>>()
+            .join("|");
+
+        Some(BinaryCache {
+            dir: dir.into(),
+            driver,
+        })
+    }
+
+    // The key for a program built from these stages (in this order) by this driver
+    pub fn key(&self, stages: &[Stage]) -> u64 {
+        let mut hash = Fnv1a::new();
+        hash.write(&VERSION.to_le_bytes());
+        hash.write(self.driver.as_bytes());
+        for stage in stages {
+            hash.write(&stage.shader_type.to_le_bytes());
+            hash.write(stage.preprocessed.source.as_bytes_with_nul());
+        }
+
+        hash.finish()
+    }
+
+    /**
+    Load the program stored under `key`, returning the id of the linked program.
+    Entries the driver rejects (eg/ after a driver update it didn't tell us about) are removed.
+    */
+    pub fn load(&self, key: u64) -> Option<gl::types::GLuint> {
+        let path = self.path_of(key);
+        let contents = fs::read(&path).ok()?;
+
+        // MAGIC, VERSION, binary format, binary
+        if contents.len() < 12 || &contents[0..4] != MAGIC || read_u32(&contents[4..8]) != VERSION {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        let format = read_u32(&contents[8..12]);
+        let binary = &contents[12..];
+
+        let program_id = unsafe { gl::CreateProgram() };
+        let mut success: gl::types::GLint = 0;
+        unsafe {
+            gl::ProgramBinary(program_id, format, binary.as_ptr() as *const gl::types::GLvoid, binary.len() as gl::types::GLsizei);
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            unsafe { gl::DeleteProgram(program_id); }
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        Some(program_id)
+    }
+
+    // Store a linked program under `key`
+    pub fn store(&self, key: u64, program_id: gl::types::GLuint) {
+        let mut length: gl::types::GLint = 0;
+        unsafe { gl::GetProgramiv(program_id, gl::PROGRAM_BINARY_LENGTH, &mut length); }
+        if length <= 0 {
+            return;
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let mut written: gl::types::GLsizei = 0;
+        let mut format: gl::types::GLenum = 0;
+        unsafe {
+            gl::GetProgramBinary(program_id, length, &mut written, &mut format, binary.as_mut_ptr() as *mut gl::types::GLvoid);
+        }
+        binary.truncate(written.max(0) as usize);
+
+        let mut contents = Vec::with_capacity(12 + binary.len());
+        contents.extend_from_slice(MAGIC);
+        contents.extend_from_slice(&VERSION.to_le_bytes());
+        contents.extend_from_slice(&format.to_le_bytes());
+        contents.extend_from_slice(&binary);
+
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(self.path_of(key), contents));
+        if let Err(e) = result {
+            eprintln!("Warning: Failed to cache program binary: {}", e);
+        }
+    }
+
+    fn path_of(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", key))
+    }
+}
+
+fn gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(string as *const std::os::raw::c_char).to_string_lossy().into_owned()
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+// FNV-1a: a simple hash that (unlike `DefaultHasher`) is guaranteed to be the same between runs and builds
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -0,0 +1,172 @@
+/*
+Turns a driver's shader info log into readable diagnostics: which file and line each message is about, with the
+source lines around it.
+
+Drivers format their logs differently. The common ones are:
+- Mesa:             `0:12(5): error: message`
+- NVIDIA:           `0(12) : error C0000: message`
+- AMD (and Intel):  `ERROR: 0:12: message`
+where `0` is the source string number, which the `#line` directives from the preprocessor map to files.
+*/
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Other,      // Anything else the driver says (or lines we couldn't parse)
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<String>,   // `None` if the log line couldn't be parsed
+    pub line: Option<usize>,
+    pub message: String,
+    pub context: Vec<(usize, String)>,  // Numbered source lines around `line`
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Other => write!(f, "note"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: {}: {}", file, line, self.severity, self.message)?,
+            (Some(file), None) => write!(f, "{}: {}: {}", file, self.severity, self.message)?,
+            (None, _) => write!(f, "{}", self.message)?,
+        }
+
+        for (number, text) in self.context.iter() {
+            let marker = if Some(*number) == self.line { ">" } else { " " };
+            write!(f, "\n {} {:>4} | {}", marker, number, text)?;
+        }
+
+        Ok(())
+    }
+}
+
+/**
+Parse an info log. `source` is the source that was compiled, and `files` the files its source string numbers
+refer to (see `preprocess::Preprocessed`).
+*/
+pub fn parse_log(log: &str, source: &str, files: &[String]) -> Vec<Diagnostic> {
+    let lines = source_lines(source);
+
+    log.lines()
+        .map(|line| line.trim_matches(|c: char| c == '\0' || c.is_whitespace()))
+        .filter(|line| !line.is_empty())
+        .map(|log_line| match parse_line(log_line) {
+            Some((severity, file_index, line, message)) => {
+                let line = Some(line).filter(|&line| line != 0);    // Line 0 is used for messages about the whole file
+                let context = line
+                    .map(|line| (line.saturating_sub(1)..=line + 1)
+                        .filter_map(|number| lines.get(&(file_index, number)).map(|&text| (number, String::from(text))))
+                        .collect())
+                    .unwrap_or_default();
+
+                Diagnostic {
+                    severity,
+                    file: Some(files.get(file_index).cloned().unwrap_or_else(|| format!("<source string {}>", file_index))),
+                    line,
+                    message,
+                    context,
+                }
+            },
+            None => Diagnostic {
+                severity: Severity::Other,
+                file: None,
+                line: None,
+                message: String::from(log_line),
+                context: Vec::new(),
+            },
+        })
+        .collect()
+}
+
+// Parse one line of a log into its severity, source string number, line number and message
+fn parse_line(line: &str) -> Option<(Severity, usize, usize, String)> {
+    // AMD and Intel: `ERROR: 0:12: message`
+    for &(prefix, severity) in [("ERROR:", Severity::Error), ("WARNING:", Severity::Warning)].iter() {
+        if let Some(rest) = line.strip_prefix(prefix) {
+            let (file, rest) = number(rest.trim_start())?;
+            let (line, rest) = number(rest.strip_prefix(':')?)?;
+            let message = rest.strip_prefix(':')?.trim();
+            return Some((severity, file, line, String::from(message)));
+        }
+    }
+
+    let (file, rest) = number(line)?;
+    let (line, rest) = if let Some(rest) = rest.strip_prefix(':') {
+        // Mesa: `0:12(5): error: message`
+        let (line, rest) = number(rest)?;
+        let rest = match rest.strip_prefix('(') {
+            Some(column) => column.split_once(')')?.1,
+            None => rest,
+        };
+        (line, rest)
+    } else if let Some(rest) = rest.strip_prefix('(') {
+        // NVIDIA: `0(12) : error C0000: message`
+        let (line, rest) = number(rest)?;
+        (line, rest.strip_prefix(')')?)
+    } else {
+        return None;
+    };
+
+    // Both then have `: <severity> [code]: message`
+    let mut parts = rest.trim_start().strip_prefix(':')?.splitn(2, ':');
+    let heading = parts.next()?.trim();
+    let message = parts.next().unwrap_or("").trim();
+    let severity = match heading.split_whitespace().next().map(str::to_lowercase).as_deref() {
+        Some("error") | Some("fatal") => Severity::Error,
+        Some("warning") => Severity::Warning,
+        _ => Severity::Other,
+    };
+
+    Some((severity, file, line, String::from(message)))
+}
+
+// Split a leading decimal number off a string
+fn number(s: &str) -> Option<(usize, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let value = s[..end].parse().ok()?;
+    Some((value, &s[end..]))
+}
+
+/**
+Work out which file and line each line of the compiled source came from, by following its `#line` directives.
+Returns (source string number, line number) -> line.
+*/
+fn source_lines(source: &str) -> HashMap<(usize, usize), &str> {
+    let mut lines = HashMap::new();
+    let mut file = 0;
+    let mut line = 1;
+
+    for text in source.lines() {
+        if let Some(directive) = text.trim_start().strip_prefix("#line") {
+            // `#line <line> [<source string number>]` gives the number of the next line
+            let mut parts = directive.split_whitespace().map(|part| part.parse::<usize>());
+            if let Some(Ok(next_line)) = parts.next() {
+                line = next_line;
+                if let Some(Ok(next_file)) = parts.next() {
+                    file = next_file;
+                }
+                continue;
+            }
+        }
+
+        lines.insert((file, line), text);
+        line += 1;
+    }
+
+    lines
+}
@@ -0,0 +1,44 @@
+/*
+Data shared by every program, updated once per frame.
+Shaders get it by including `common/frame.glsl`, which must match `FrameData`.
+*/
+
+extern crate nalgebra;
+
+use nalgebra::{Matrix4, Vector4};
+
+// Laid out to match the std140 `FrameData` block
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct FrameData {
+    pub view: Matrix4<f32>,
+    pub projection: Matrix4<f32>,
+    pub fog_colour: Vector4<f32>,
+    pub sun_direction: Vector4<f32>,    // Direction the sunlight travels in (w is unused)
+    pub sun_colour: Vector4<f32>,
+    pub time: f32,      // Seconds since the game started
+    pub fog_start: f32, // Distance fog starts at
+    pub fog_end: f32,   // Distance everything is fully fogged at
+    _padding: f32,      // std140 rounds blocks up to a multiple of 16 bytes
+}
+
+impl FrameData {
+    pub const BLOCK_NAME: &'static str = "FrameData";
+    pub const BINDING: gl::types::GLuint = 0;
+}
+
+impl Default for FrameData {
+    fn default() -> Self {
+        FrameData {
+            view: Matrix4::identity(),
+            projection: Matrix4::identity(),
+            fog_colour: Vector4::new(0.3, 0.3, 0.5, 1.0),
+            sun_direction: Vector4::new(-0.3, -1.0, -0.2, 0.0),
+            sun_colour: Vector4::new(1.0, 1.0, 0.9, 1.0),
+            time: 0.0,
+            fog_start: 64.0,
+            fog_end: 128.0,
+            _padding: 0.0,
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/*
+Background asset loading.
+
+Reading files, decoding images and preprocessing shaders happens on worker threads. The finished buffers are queued up for the
+main thread, which owns the GL context, to turn into GL objects. The main thread only spends a bounded
+amount of time on that each frame, so it can keep drawing (eg/ a loading screen) while assets load.
+*/
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::resources::Resources;
+use crate::resources::decode::DecodedImage;
+use crate::render::shader::{self, Stage};
+use crate::render::program::Program;
+use crate::render::binary_cache::BinaryCache;
+use crate::render::texture::{animation, Animation, Texture, TextureBuilder};
+use crate::render::Error;
+
+// Work for the worker threads
+enum Request {
+    Texture { name: String, path: String, builder: TextureBuilder },
+    Program { name: String, shader_names: Vec<String> },
+}
+
+// Work that's been done on a worker thread, ready to be uploaded
+enum Decoded {
+    Texture { name: String, path: String, builder: TextureBuilder, result: Result<(DecodedImage, Option<Animation>), Error> },
+    Program { name: String, result: Result<Vec<Stage>, Error> },
+}
+
+// A finished GL object
+pub enum Loaded {
+    // Animated images are loaded as their first frame, with the animation to play (see `texture::animation`)
+    Texture { name: String, result: Result<Texture, Error>, animation: Option<Animation> },
+    Program { name: String, result: Result<Program, Error> },
+}
+
+pub struct Loader {
+    requests: Option<mpsc::Sender<Request>>,    // Only `None` while shutting down
+    decoded: mpsc::Receiver<Decoded>,
+    workers: Vec<thread::JoinHandle<()>>,
+    binary_cache: Option<BinaryCache>,
+
+    requested: usize,
+    completed: usize,
+    disconnected: bool,     // Every worker has stopped, so nothing more will be decoded
+}
+
+impl Loader {
+    // Start a loader with the given number of worker threads
+    pub fn new(res: Arc<Resources>, worker_count: usize) -> Loader {
+        let (request_sender, request_receiver) = mpsc::channel::<Request>();
+        let (decoded_sender, decoded_receiver) = mpsc::channel::<Decoded>();
+
+        // All the workers take requests from the same queue
+        let request_receiver = Arc::new(Mutex::new(request_receiver));
+
+        let workers = (0..worker_count.max(1))
+            .map(|_| {
+                let res = Arc::clone(&res);
+                let requests = Arc::clone(&request_receiver);
+                let decoded = decoded_sender.clone();
+                thread::spawn(move || worker(&res, &requests, &decoded))
+            })
+            .collect();
+
+        Loader {
+            requests: Some(request_sender),
+            decoded: decoded_receiver,
+            workers,
+            binary_cache: None,
+            requested: 0,
+            completed: 0,
+            disconnected: false,
+        }
+    }
+
+    // Queue a texture to be loaded. It's loaded under its resource name.
+    pub fn load_texture(&mut self, path: &str) {
+        self.load_texture_with(path, path, TextureBuilder::new());
+    }
+
+    /**
+    Queue a texture to be loaded from the image resource `path`, and built with the given settings.
+    `name` is just for telling the textures apart, as one image can be built with several settings.
+    */
+    pub fn load_texture_with(&mut self, name: &str, path: &str, builder: TextureBuilder) {
+        self.request(Request::Texture { name: String::from(name), path: String::from(path), builder });
+    }
+
+    // Queue a program to be built from some shader resources. `name` is just for telling the programs apart.
+    pub fn load_program<S: AsRef<str>>(&mut self, name: &str, shader_names: &[S]) {
+        self.request(Request::Program {
+            name: String::from(name),
+            shader_names: shader_names.iter().map(|name| String::from(name.as_ref())).collect(),
+        });
+    }
+
+    // Load programs from (and store them in) a binary cache
+    pub fn set_binary_cache(&mut self, binary_cache: Option<BinaryCache>) {
+        self.binary_cache = binary_cache;
+    }
+
+    fn request(&mut self, request: Request) {
+        if let Some(requests) = &self.requests {
+            // Sending only fails if every worker has died, in which case nothing can be loaded anyway
+            if requests.send(request).is_ok() {
+                self.requested += 1;
+            }
+        }
+    }
+
+    /**
+    Create GL objects from anything that's finished decoding.
+    Must be called on the thread that owns the GL context.
+    Stops once `budget` has been used up (but always uploads at least one item if one is ready).
+    */
+    pub fn upload(&mut self, budget: Duration) -> Vec<Loaded> {
+        let start = Instant::now();
+        let mut loaded = Vec::new();
+
+        while loaded.is_empty() || start.elapsed() < budget {
+            let decoded = match self.decoded.try_recv() {
+                Ok(decoded) => decoded,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.disconnected = true;
+                    break;
+                },
+            };
+
+            loaded.push(upload(decoded, self.binary_cache.as_ref()));
+            self.completed += 1;
+        }
+
+        loaded
+    }
+
+    // How many items have been loaded, out of how many requested
+    pub fn progress(&self) -> (usize, usize) {
+        (self.completed, self.requested)
+    }
+
+    /**
+    Whether everything requested has been loaded. Also true if the workers have all stopped, as then nothing else
+    will be, so whatever's missing should be reported rather than waited for.
+    */
+    pub fn is_done(&self) -> bool {
+        self.completed == self.requested || self.disconnected
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        // Closing the request queue stops the workers once they've finished what they're doing
+        self.requests = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker(res: &Resources, requests: &Mutex<mpsc::Receiver<Request>>, decoded: &mpsc::Sender<Decoded>) {
+    loop {
+        // Only hold the lock while waiting for a request, not while working on it.
+        // (A worker can't panic while holding it, so the queue is fine even if it's been poisoned)
+        let request = match requests.lock().unwrap_or_else(PoisonError::into_inner).recv() {
+            Ok(request) => request,
+            Err(_) => return,   // The loader has been dropped
+        };
+
+        // A panic (eg/ in a decoder) still has to complete the request, or the loader would wait for it forever
+        let result = panic::catch_unwind(AssertUnwindSafe(|| decode(res, &request)))
+            .unwrap_or_else(|payload| request.failed(Error::Panic {
+                name: String::from(request.name()),
+                message: panic_message(payload.as_ref()),
+            }));
+
+        if decoded.send(result).is_err() {
+            return;
+        }
+    }
+}
+
+// Do the work for a request that doesn't need the GL context
+fn decode(res: &Resources, request: &Request) -> Decoded {
+    match request {
+        Request::Texture { name, path, builder } => {
+            // Animated textures start on their first frame
+            let result = animation::load_animated(res, path);
+            Decoded::Texture { name: name.clone(), path: path.clone(), builder: builder.clone(), result }
+        },
+        Request::Program { name, shader_names } => {
+            let result = shader_names.iter()
+                .map(|shader_name| shader::load_stages(res, shader_name, &[]))
+                .collect::<Result<Vec<Vec<Stage>>, Error>>()
+                .map(|stages| stages.into_iter().flatten().collect());
+            Decoded::Program { name: name.clone(), result }
+        },
+    }
+}
+
+impl Request {
+    fn name(&self) -> &str {
+        match self {
+            Request::Texture { name, .. } => name,
+            Request::Program { name, .. } => name,
+        }
+    }
+
+    // The result of a request that couldn't be done
+    fn failed(&self, error: Error) -> Decoded {
+        let name = String::from(self.name());
+        match self {
+            Request::Texture { path, builder, .. } => Decoded::Texture {
+                name, path: path.clone(), builder: builder.clone(), result: Err(error)
+            },
+            Request::Program { .. } => Decoded::Program { name, result: Err(error) },
+        }
+    }
+}
+
+// What a panic was about, if it said
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|message| String::from(*message))
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("Unknown panic"))
+}
+
+// Create the GL object for some decoded data
+fn upload(decoded: Decoded, binary_cache: Option<&BinaryCache>) -> Loaded {
+    match decoded {
+        Decoded::Texture { name, path, builder, result } => {
+            let (result, animation) = match result {
+                Ok((image, animation)) => (builder.build(&image, &path), animation),
+                Err(e) => (Err(e), None),
+            };
+            Loaded::Texture { name, result, animation }
+        },
+        Decoded::Program { name, result } => {
+            let result = result.and_then(|stages| Program::from_stages(&stages, binary_cache));
+            Loaded::Program { name, result }
+        },
+    }
+}
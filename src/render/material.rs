@@ -0,0 +1,88 @@
+/*
+A material is everything needed to draw with a program: the program itself, the textures its samplers read
+(each on its own texture unit), and values for its other uniforms.
+*/
+
+use std::rc::Rc;
+
+use crate::assets::Handle;
+use crate::render::program::Program;
+use crate::render::texture::Texture;
+use crate::render::uniform::{Sampler, UniformValue};
+
+struct TextureBinding {
+    uniform: String,    // The sampler uniform that reads the texture
+    unit: u32,
+    texture: Handle<Texture>,
+}
+
+pub struct Material {
+    program: Handle<Program>,
+    textures: Vec<TextureBinding>,
+    uniforms: Vec<(String, UniformValue)>,
+}
+
+impl Material {
+    pub fn new(program: Handle<Program>) -> Material {
+        Material {
+            program,
+            textures: Vec::new(),
+            uniforms: Vec::new(),
+        }
+    }
+
+    /**
+    Set the texture a sampler uniform reads. The first texture set for each sampler gets the next free texture unit,
+    later ones replace it on the same unit.
+    */
+    pub fn set_texture(&mut self, uniform: &str, texture: Handle<Texture>) {
+        match self.textures.iter_mut().find(|binding| binding.uniform == uniform) {
+            Some(binding) => binding.texture = texture,
+            None => {
+                let unit = self.textures.len() as u32;
+                self.textures.push(TextureBinding { uniform: String::from(uniform), unit, texture });
+            },
+        }
+    }
+
+    // Replace a texture wherever it's used (eg/ after it's been reloaded)
+    pub fn replace_texture(&mut self, old: &Handle<Texture>, new: &Handle<Texture>) {
+        for binding in self.textures.iter_mut().filter(|binding| Rc::ptr_eq(&binding.texture, old)) {
+            binding.texture = Rc::clone(new);
+        }
+    }
+
+    // Set the value a uniform is given whenever the material is applied
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        match self.uniforms.iter_mut().find(|(uniform, _)| uniform == name) {
+            Some((_, current)) => *current = value,
+            None => self.uniforms.push((String::from(name), value)),
+        }
+    }
+
+    // Swap the program (eg/ after it's been reloaded). The program must use the same uniforms.
+    pub fn set_program(&mut self, program: Handle<Program>) {
+        self.program = program;
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /**
+    Get ready to draw with the material: use its program, bind its textures and set its uniforms.
+    Uniforms can be set on `program()` afterwards, for anything that varies between draws.
+    */
+    pub fn apply(&self) {
+        self.program.set();
+
+        for binding in self.textures.iter() {
+            binding.texture.bind(binding.unit);
+            self.program.set_uniform(&binding.uniform, Sampler(binding.unit));
+        }
+
+        for (name, value) in self.uniforms.iter() {
+            self.program.set_uniform(name, value);
+        }
+    }
+}
@@ -2,3 +2,105 @@
 pub mod shader;
 pub mod program;
 pub mod texture;
+pub mod reload;
+pub mod loader;
+pub mod preprocess;
+pub mod uniform;
+pub mod uniform_buffer;
+pub mod frame;
+pub mod binary_cache;
+pub mod diagnostics;
+pub mod material;
+pub mod variants;
+
+use std::fmt;
+use crate::resources;
+use crate::resources::decode::PixelFormat;
+use self::diagnostics::Diagnostic;
+
+// Errors from creating render objects
+#[derive(Debug)]
+pub enum Error {
+    // A resource couldn't be loaded
+    Resource { name: String, inner: resources::Error },
+    // The shader type can't be determined from the resource name
+    UnknownShaderType { name: String },
+    // A shader's source couldn't be preprocessed (eg/ a bad `#include`)
+    Preprocess { name: String, line: usize, message: String },
+    // A shader failed to compile: `log` is the driver's info log, and `diagnostics` what could be made of it
+    Compile { name: String, log: String, diagnostics: Vec<Diagnostic> },
+    // A program failed to link: `name` lists the shaders that were linked
+    Link { name: String, log: String },
+    // A program reads vertex attributes that a vertex type doesn't supply (or supplies differently)
+    VertexLayout { name: String, vertex: String, problems: Vec<String> },
+    // Something needs a newer OpenGL than the context supports
+    Unsupported { name: String, feature: &'static str },
+    // An image was decoded, but its pixels can't be uploaded as a texture
+    UnsupportedPixelFormat { name: String, format: PixelFormat },
+    // An image is too large for OpenGL to take its size
+    ImageTooLarge { name: String, size: (u32, u32) },
+    // The images given to an atlas don't fit in the largest texture the driver supports
+    AtlasTooLarge { name: String, max_size: u32 },
+    // An image isn't the size of the texture array layer (or atlas image) it's for
+    LayerSize { name: String, expected: (u32, u32), found: (u32, u32) },
+    // An image was given a texture array layer that doesn't exist
+    LayerOutOfRange { name: String, layer: u32, layer_count: usize },
+    // An animated texture's sidecar is invalid, or doesn't match its image
+    Animation { name: String, message: String },
+    // A loader worker thread panicked while working on a resource
+    Panic { name: String, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Resource { name, inner } => write!(f, "Error loading resource {}: {}", name, inner),
+            Error::UnknownShaderType { name } => write!(f, "Can not determine shader type for resource {}", name),
+            Error::Preprocess { name, line, message } => write!(f, "Failed to preprocess shader {} (line {}): {}", name, line, message),
+            Error::Compile { name, log, diagnostics } => {
+                write!(f, "Failed to compile shader {}:", name)?;
+                if diagnostics.is_empty() {
+                    return write!(f, "\n{}", trim_log(log));
+                }
+                for diagnostic in diagnostics {
+                    write!(f, "\n{}", diagnostic)?;
+                }
+                Ok(())
+            },
+            Error::Link { name, log } => write!(f, "Failed to link program ({}):\n{}", name, trim_log(log)),
+            Error::VertexLayout { name, vertex, problems } => {
+                write!(f, "Program ({}) can not draw {} vertices:", name, vertex)?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            },
+            Error::Unsupported { name, feature } => write!(f, "{} uses {}, which this OpenGL context doesn't support", name, feature),
+            Error::UnsupportedPixelFormat { name, format } => write!(f, "Can not create a texture from {} with pixel format {:?}", name, format),
+            Error::ImageTooLarge { name, size } => write!(f, "Image {} is {}x{}, which is too large for a texture", name, size.0, size.1),
+            Error::AtlasTooLarge { name, max_size } => write!(f, "Atlas {} does not fit in a {}x{} texture", name, max_size, max_size),
+            Error::LayerSize { name, expected, found } => write!(
+                f, "Image {} is {}x{}, but the texture layer (or atlas image) it's for is {}x{}", name, found.0, found.1, expected.0, expected.1
+            ),
+            Error::LayerOutOfRange { name, layer, layer_count } => write!(
+                f, "Image {} is for layer {}, but the texture array only has {} layers", name, layer, layer_count
+            ),
+            Error::Animation { name, message } => write!(f, "Invalid animation for {}: {}", name, message),
+            Error::Panic { name, message } => write!(f, "Loading {} panicked: {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Resource { inner, .. } => Some(inner),
+            _ => None,
+        }
+    }
+}
+
+// Info logs come back from the driver with a null terminator and trailing whitespace
+fn trim_log(log: &str) -> &str {
+    log.trim_end_matches(|c: char| c == '\0' || c.is_whitespace())
+}
@@ -0,0 +1,192 @@
+/*
+A small preprocessor, run on shader sources before they're compiled:
+
+- `#include "common/quat.glsl"` is replaced by the contents of that resource, found relative to the including file.
+  Each file is only included once per shader (so helpers can include the helpers they use), but a file including
+  itself, directly or through other files, is an error.
+- Defines given by the caller are inserted straight after the `#version` line.
+- `#line` directives are inserted so the driver still reports errors against the original files and lines.
+  The "source string number" in the driver's log is an index into `Preprocessed::files`.
+*/
+
+use std::ffi::CString;
+
+use crate::resources::{self, Resources};
+use crate::resources::path::ResourcePath;
+use crate::render::Error;
+
+pub struct Preprocessed {
+    pub source: CString,
+    pub files: Vec<String>,     // Every file that went into the source. The first is the shader itself.
+}
+
+// Preprocess the named shader. `defines` are `(name, value)` pairs, eg/ `("MAX_LIGHTS", "4")`.
+pub fn preprocess(res: &Resources, name: &str, defines: &[(&str, &str)]) -> Result<Preprocessed, Error> {
+    let source = load(res, name)?;
+    preprocess_source(res, name, &source, defines)
+}
+
+// Preprocess a shader's source, which has already been loaded from the named resource
+pub fn preprocess_source(res: &Resources, name: &str, source: &str, defines: &[(&str, &str)]) -> Result<Preprocessed, Error> {
+    let path = ResourcePath::new(name)
+        .map_err(|e| Error::Resource { name: String::from(name), inner: e })?;
+
+    let mut preprocessor = Preprocessor {
+        res,
+        files: Vec::new(),
+        stack: Vec::new(),
+        output: String::new(),
+    };
+    let defined = preprocessor.process(&path, source, defines)?;
+
+    // Without a `#version` line, the defines go right at the top
+    let mut output = preprocessor.output;
+    if !defined && !defines.is_empty() {
+        output = format!("{}#line 1 0\n{}", define_lines(defines), output);
+    }
+
+    let source = CString::new(output)
+        .map_err(|_| Error::Resource { name: String::from(name), inner: resources::Error::FileContainsNullByte })?;
+
+    Ok(Preprocessed {
+        source,
+        files: preprocessor.files.iter().map(|path| String::from(path.as_str())).collect(),
+    })
+}
+
+struct Preprocessor<'a> {
+    res: &'a Resources,
+    files: Vec<ResourcePath>,   // Files included so far, in the order they were first included
+    stack: Vec<ResourcePath>,   // The file being processed, and the files that included it
+    output: String,
+}
+
+impl<'a> Preprocessor<'a> {
+    // Process one file into the output. Returns whether the defines were inserted (after a `#version` line).
+    fn process(&mut self, path: &ResourcePath, source: &str, defines: &[(&str, &str)]) -> Result<bool, Error> {
+        let index = self.files.len();
+        self.files.push(path.clone());
+        self.stack.push(path.clone());
+
+        // Note: Since GLSL 3.30, `#line N` gives the number of the line after the directive
+        if index != 0 {
+            self.output.push_str(&format!("#line 1 {}\n", index));
+        }
+
+        let mut defined = false;
+        for (line_index, line) in source.lines().enumerate() {
+            let line_number = line_index + 1;
+            let directive = line.trim_start();
+
+            if index == 0 && !defined && directive.starts_with("#version") {
+                self.output.push_str(line);
+                self.output.push('\n');
+                self.output.push_str(&define_lines(defines));
+                self.output.push_str(&format!("#line {} 0\n", line_number + 1));
+                defined = true;
+            } else if directive.starts_with("#include") {
+                self.include(path, line_number, directive)?;
+                self.output.push_str(&format!("#line {} {}\n", line_number + 1, index));
+            } else {
+                self.output.push_str(line);
+                self.output.push('\n');
+            }
+        }
+
+        self.stack.pop();
+        Ok(defined)
+    }
+
+    // Handle an `#include` directive found in `path`
+    fn include(&mut self, path: &ResourcePath, line: usize, directive: &str) -> Result<(), Error> {
+        let error = |message: String| Error::Preprocess { name: String::from(path.as_str()), line, message };
+
+        let target = directive["#include".len()..].trim();
+        if target.len() < 2 || !target.starts_with('"') || !target.ends_with('"') {
+            return Err(error(format!("Expected `#include \"file\"`, found `{}`", directive)));
+        }
+        let target = &target[1..target.len() - 1];
+
+        let included = path.relative(target)
+            .map_err(|e| error(format!("Can not include {}: {}", target, e)))?;
+
+        if self.stack.contains(&included) {
+            let chain: Vec<&str> = self.stack.iter().map(ResourcePath::as_str).collect();
+            return Err(error(format!("Include cycle: {} -> {}", chain.join(" -> "), included)));
+        }
+        if self.files.contains(&included) {
+            return Ok(());  // Already included
+        }
+
+        let source = load_source(self.res, &included)
+            .map_err(|e| error(format!("Can not include {}: {}", target, e)))?;
+        self.process(&included, &source, &[])?;
+
+        Ok(())
+    }
+}
+
+// Load a shader's source
+pub fn load(res: &Resources, name: &str) -> Result<String, Error> {
+    let path = ResourcePath::new(name)
+        .map_err(|e| Error::Resource { name: String::from(name), inner: e })?;
+    load_source(res, &path)
+        .map_err(|e| Error::Resource { name: String::from(name), inner: e })
+}
+
+/**
+Split the source of a single file shader into its stages. Each `#stage <stage>` line starts a stage, and anything
+before the first one (eg/ `#version`, and shared functions) is part of every stage.
+Returns each stage's name, the line it started on, and its source. The lines that aren't part of a stage are
+blanked out rather than removed, so line numbers in errors still match the file.
+*/
+pub fn split_stages(source: &str) -> Vec<(&str, usize, String)> {
+    // Work out which stage each line belongs to: `None` for the shared lines before the first marker
+    let mut stages: Vec<(&str, usize)> = Vec::new();
+    let mut owners: Vec<Option<usize>> = Vec::new();
+    let mut markers: Vec<bool> = Vec::new();
+    let mut current = None;
+    for (line_index, line) in source.lines().enumerate() {
+        let directive = line.trim_start();
+        let is_marker = directive.starts_with("#stage");
+        if is_marker {
+            let stage = directive["#stage".len()..].trim();
+            // A stage can be split over several sections
+            current = match stages.iter().position(|&(name, _)| name == stage) {
+                Some(index) => Some(index),
+                None => {
+                    stages.push((stage, line_index + 1));
+                    Some(stages.len() - 1)
+                },
+            };
+        }
+        owners.push(current);
+        markers.push(is_marker);
+    }
+
+    stages.iter().enumerate()
+        .map(|(index, &(name, start))| {
+            let mut stage_source = String::new();
+            for (line_index, line) in source.lines().enumerate() {
+                let owner = owners[line_index];
+                if !markers[line_index] && (owner.is_none() || owner == Some(index)) {
+                    stage_source.push_str(line);
+                }
+                stage_source.push('\n');
+            }
+            (name, start, stage_source)
+        })
+        .collect()
+}
+
+fn load_source(res: &Resources, path: &ResourcePath) -> Result<String, resources::Error> {
+    let bytes = res.load_bytes(path.as_str())?;
+    String::from_utf8(bytes)
+        .map_err(|e| resources::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+}
+
+fn define_lines(defines: &[(&str, &str)]) -> String {
+    defines.iter()
+        .map(|(name, value)| format!("#define {} {}\n", name, value))
+        .collect()
+}
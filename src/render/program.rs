@@ -1,17 +1,41 @@
 extern crate gl;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+
 // pub mod shader;
-use crate::render::shader;
+use crate::resources::Resources;
+use crate::render::shader::{self, Stage};
+use crate::render::binary_cache::BinaryCache;
+use crate::render::uniform::Uniform;
+use crate::render::uniform_buffer::BLOCK_BINDINGS;
+use crate::render::Error;
+use crate::obj::vertex::Vertex;
 use crate::ffi_utils;
 
+// An active attribute or uniform of a linked program
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub gl_type: gl::types::GLenum,     // eg/ `gl::FLOAT_VEC3`
+    pub size: gl::types::GLint,         // Array length (1 if not an array)
+    pub location: gl::types::GLint,     // -1 for uniforms in a uniform block
+}
+
 // Shader program
 pub struct Program {
     id: gl::types::GLuint,
+    name: String,   // The shaders it was linked from, for error reporting
+    files: Vec<String>,     // Every resource the program was built from
+    attributes: Vec<Variable>,
+    uniforms: Vec<Variable>,
+    uniform_locations: RefCell<HashMap<String, gl::types::GLint>>,  // Uniform name -> location (-1 if it doesn't exist)
 }
 
 impl Program {
-    // Compile a program from a collection of Shaders
-    pub fn from_shaders(shaders: &[shader::Shader]) -> Result<Program, String> {
+    // Compile a program from a collection of Shaders. They're already compiled, so this skips the binary cache.
+    pub fn from_shaders(shaders: &[shader::Shader]) -> Result<Program, Error> {
         // Create a program
         let program_id = unsafe { gl::CreateProgram() };
 
@@ -21,7 +45,14 @@ impl Program {
         }
 
         // Link the program
-        unsafe { gl::LinkProgram(program_id); }
+        unsafe {
+            // Let the driver know we may want to store the linked program
+            if gl::ProgramParameteri::is_loaded() {
+                gl::ProgramParameteri(program_id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as gl::types::GLint);
+            }
+            gl::LinkProgram(program_id);
+        }
+        let name = shaders.iter().map(|shader| shader.name()).collect::<Vec<&str>>().join(", ");
 
         // Check that the program linked correctly
         let mut success: gl::types::GLint = 1;
@@ -47,7 +78,10 @@ impl Program {
             }
 
 
-            return Err(error.to_string_lossy().into_owned());
+            // Clean up the failed program
+            unsafe { gl::DeleteProgram(program_id); }
+
+            return Err(Error::Link { name, log: error.to_string_lossy().into_owned() });
         }
 
         // After linking, detach the shaders
@@ -55,18 +89,211 @@ impl Program {
             unsafe { gl::DetachShader(program_id, shader.id()); }
         }
 
-        Ok(Program { id: program_id })
+        let mut files: Vec<String> = shaders.iter()
+            .flat_map(|shader| shader.files().iter().cloned())
+            .collect();
+        files.sort();
+        files.dedup();
+
+        Ok(Program::from_linked(program_id, name, files))
+    }
+
+    /**
+    Build a program from shader resources (one per stage, or multi-stage `.glsl` files), with the given defines.
+    Goes through the binary cache if there is one (see `from_stages`).
+    */
+    pub fn from_resources<S: AsRef<str>>(
+        res: &Resources,
+        shader_names: &[S],
+        defines: &[(&str, &str)],
+        binary_cache: Option<&BinaryCache>
+    ) -> Result<Program, Error> {
+        let mut stages = Vec::new();
+        for name in shader_names {
+            stages.extend(shader::load_stages(res, name.as_ref(), defines)?);
+        }
+
+        Program::from_stages(&stages, binary_cache)
+    }
+
+    /**
+    Build a program from preprocessed stages. If there's a binary cache, the program is loaded from it when
+    possible (skipping compiling and linking), and stored in it when not.
+    */
+    pub fn from_stages(stages: &[Stage], binary_cache: Option<&BinaryCache>) -> Result<Program, Error> {
+        let key = binary_cache.map(|cache| cache.key(stages));
+        if let (Some(cache), Some(key)) = (binary_cache, key) {
+            if let Some(program_id) = cache.load(key) {
+                let name = stages.iter().map(|stage| stage.preprocessed.files[0].as_str()).collect::<Vec<&str>>().join(", ");
+                let mut files: Vec<String> = stages.iter()
+                    .flat_map(|stage| stage.preprocessed.files.iter().cloned())
+                    .collect();
+                files.sort();
+                files.dedup();
+
+                return Ok(Program::from_linked(program_id, name, files));
+            }
+        }
+
+        let shaders = stages.iter()
+            .map(shader::Shader::from_stage)
+            .collect::<Result<Vec<shader::Shader>, Error>>()?;
+        let program = Program::from_shaders(&shaders)?;
+
+        if let (Some(cache), Some(key)) = (binary_cache, key) {
+            cache.store(key, program.id);
+        }
+
+        Ok(program)
+    }
+
+    // Wrap a successfully linked program
+    fn from_linked(program_id: gl::types::GLuint, name: String, files: Vec<String>) -> Program {
+        // Find out what the program takes as input
+        let attributes = active_variables(program_id, gl::ACTIVE_ATTRIBUTES, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, gl::GetActiveAttrib, gl::GetAttribLocation);
+        let uniforms = active_variables(program_id, gl::ACTIVE_UNIFORMS, gl::ACTIVE_UNIFORM_MAX_LENGTH, gl::GetActiveUniform, gl::GetUniformLocation);
+
+        // Attach any uniform blocks to their binding points
+        for &(block_name, binding) in BLOCK_BINDINGS.iter() {
+            bind_uniform_block(program_id, block_name, binding);
+        }
+
+        // We already know where the active uniforms are
+        let uniform_locations = uniforms.iter()
+            .map(|uniform| (uniform.name.clone(), uniform.location))
+            .collect();
+
+        Program {
+            id: program_id,
+            name,
+            files,
+            attributes,
+            uniforms,
+            uniform_locations: RefCell::new(uniform_locations),
+        }
     }
 
     pub fn id(&self) -> gl::types::GLuint {
         self.id
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // The resources the program was built from (its shaders and their includes)
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    // -- Reflection -- //
+    // The vertex attributes the program uses (`gl_` built-ins included)
+    pub fn attributes(&self) -> &[Variable] {
+        &self.attributes
+    }
+
+    // The uniforms the program uses. Arrays are listed once, as `name[0]`.
+    pub fn uniforms(&self) -> &[Variable] {
+        &self.uniforms
+    }
+
+    /**
+    Check that vertices of type `T` supply every attribute the program reads, at the right location and with
+    the right number of components. GL would otherwise quietly fill in anything missing.
+    */
+    pub fn check_vertex_layout<T: Vertex>(&self) -> Result<(), Error> {
+        let vertex = std::any::type_name::<T>().rsplit("::").next().unwrap_or("vertex");
+        let mut problems = Vec::new();
+
+        for attribute in self.attributes.iter().filter(|attribute| !attribute.name.starts_with("gl_")) {
+            let expected = match float_components(attribute.gl_type) {
+                Some(components) => components,
+                None => {
+                    problems.push(format!("`{}` (location {}) is not a float or float vector, which vertices can't supply", attribute.name, attribute.location));
+                    continue;
+                },
+            };
+
+            match T::attributes().iter().find(|supplied| supplied.location as gl::types::GLint == attribute.location) {
+                None => problems.push(format!(
+                    "`{}` expects {} floats at location {}, but {} has nothing there",
+                    attribute.name, expected, attribute.location, vertex
+                )),
+                Some(supplied) if supplied.components != expected => problems.push(format!(
+                    "`{}` expects {} floats at location {}, but {} has `{}` there, with {}",
+                    attribute.name, expected, attribute.location, vertex, supplied.name, supplied.components
+                )),
+                Some(_) => {},
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::VertexLayout { name: self.name.clone(), vertex: String::from(vertex), problems })
+        }
+    }
+
     pub fn set(&self) {
         unsafe {
             gl::UseProgram(self.id);
         }
     }
+
+    /**
+    Attach a uniform block to a binding point. Returns whether the program has the block.
+    Note: Blocks in `uniform_buffer::BLOCK_BINDINGS` are attached when the program is linked.
+    */
+    pub fn bind_uniform_block(&self, block_name: &str, binding: gl::types::GLuint) -> bool {
+        bind_uniform_block(self.id, block_name, binding)
+    }
+
+    /**
+    Run a compute program over a grid of work groups. This sets the program as the one in use.
+    Note: Use `gl::MemoryBarrier` before reading anything the program writes.
+    */
+    pub fn dispatch_compute(&self, groups_x: u32, groups_y: u32, groups_z: u32) -> Result<(), Error> {
+        if !gl::DispatchCompute::is_loaded() {
+            return Err(Error::Unsupported { name: self.name.clone(), feature: "compute shaders" });
+        }
+
+        self.set();
+        unsafe {
+            gl::DispatchCompute(groups_x, groups_y, groups_z);
+        }
+
+        Ok(())
+    }
+
+    // -- Uniforms -- //
+    /**
+    Set a uniform by name. The program must be the one in use (see `set()`).
+    Unknown uniforms (including any the driver optimised out) are ignored, with a warning the first time.
+    */
+    pub fn set_uniform<U: Uniform>(&self, name: &str, value: U) {
+        value.set_at(self.uniform_location(name).unwrap_or(-1));
+    }
+
+    /**
+    Get the location of a uniform, or `None` if the program doesn't have it.
+    Locations are looked up once, then cached.
+    */
+    pub fn uniform_location(&self, name: &str) -> Option<gl::types::GLint> {
+        if let Some(&location) = self.uniform_locations.borrow().get(name) {
+            return Some(location).filter(|&location| location != -1);
+        }
+
+        let location = match CString::new(name) {
+            Ok(c_name) => unsafe { gl::GetUniformLocation(self.id, c_name.as_ptr()) },
+            Err(_) => -1,
+        };
+        if location == -1 {
+            eprintln!("Warning: Program ({}) has no uniform {}", self.name, name);
+        }
+        self.uniform_locations.borrow_mut().insert(String::from(name), location);
+
+        Some(location).filter(|&location| location != -1)
+    }
 }
 
 impl Drop for Program {
@@ -77,3 +304,81 @@ impl Drop for Program {
         }
     }
 }
+
+fn bind_uniform_block(program_id: gl::types::GLuint, block_name: &str, binding: gl::types::GLuint) -> bool {
+    let c_name = match CString::new(block_name) {
+        Ok(c_name) => c_name,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        let index = gl::GetUniformBlockIndex(program_id, c_name.as_ptr());
+        if index == gl::INVALID_INDEX {
+            return false;
+        }
+        gl::UniformBlockBinding(program_id, index, binding);
+    }
+
+    true
+}
+
+type GetActive = unsafe fn(
+    gl::types::GLuint, gl::types::GLuint, gl::types::GLsizei,
+    *mut gl::types::GLsizei, *mut gl::types::GLint, *mut gl::types::GLenum, *mut gl::types::GLchar
+);
+type GetLocation = unsafe fn(gl::types::GLuint, *const gl::types::GLchar) -> gl::types::GLint;
+
+// List a linked program's active attributes or uniforms
+fn active_variables(
+    program_id: gl::types::GLuint,
+    count_param: gl::types::GLenum,
+    max_length_param: gl::types::GLenum,
+    get_active: GetActive,
+    get_location: GetLocation
+) -> Vec<Variable> {
+    let mut count: gl::types::GLint = 0;
+    let mut max_length: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program_id, count_param, &mut count);
+        gl::GetProgramiv(program_id, max_length_param, &mut max_length);
+    }
+
+    (0..count as gl::types::GLuint)
+        .map(|index| {
+            let mut buffer = vec![0u8; max_length.max(1) as usize];
+            let mut length: gl::types::GLsizei = 0;
+            let mut size: gl::types::GLint = 0;
+            let mut gl_type: gl::types::GLenum = 0;
+            unsafe {
+                get_active(
+                    program_id, index, max_length,
+                    &mut length, &mut size, &mut gl_type,
+                    buffer.as_mut_ptr() as *mut gl::types::GLchar
+                );
+            }
+            buffer.truncate(length as usize);
+
+            // `buffer` doesn't contain a null byte, as it's been cut down to the name's length
+            let c_name = CString::new(buffer).unwrap();
+            let location = unsafe { get_location(program_id, c_name.as_ptr()) };
+
+            Variable {
+                name: c_name.to_string_lossy().into_owned(),
+                gl_type,
+                size,
+                location,
+            }
+        })
+        .collect()
+}
+
+// Number of components in a float (or float vector) GLSL type
+fn float_components(gl_type: gl::types::GLenum) -> Option<gl::types::GLint> {
+    match gl_type {
+        gl::FLOAT => Some(1),
+        gl::FLOAT_VEC2 => Some(2),
+        gl::FLOAT_VEC3 => Some(3),
+        gl::FLOAT_VEC4 => Some(4),
+        _ => None,
+    }
+}
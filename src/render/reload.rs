@@ -0,0 +1,98 @@
+/*
+Textures that can be rebuilt from their resources while the game is running.
+Pair these with a `resources::watcher::Watcher` to pick up edits to the files behind them.
+(Programs are rebuilt through `render::variants::ProgramVariants::reload`)
+*/
+
+use std::rc::Rc;
+
+use crate::resources::Resources;
+use crate::assets::Handle;
+use crate::render::texture::{animation, Animation, Texture, TextureBuilder};
+use crate::render::Error;
+
+/**
+A Texture that remembers which image resource it was loaded from.
+Animated images are loaded as their first frame, and played by `update_animation`.
+*/
+pub struct ReloadableTexture {
+    name: String,
+    builder: TextureBuilder,    // How the texture is (re)built
+    texture: Handle<Texture>,
+    animation: Option<Animation>,
+}
+
+impl ReloadableTexture {
+    pub fn from_resource(res: &Resources, name: &str, builder: TextureBuilder) -> Result<ReloadableTexture, Error> {
+        let (image, animation) = animation::load_animated(res, name)?;
+        Ok(ReloadableTexture {
+            name: String::from(name),
+            texture: Rc::new(builder.build(&image, name)?),
+            builder,
+            animation,
+        })
+    }
+
+    // Wrap an already loaded texture, which was built with `builder` (from the animation's first frame, if it has one)
+    pub fn from_texture(name: &str, builder: TextureBuilder, texture: Texture, animation: Option<Animation>) -> ReloadableTexture {
+        ReloadableTexture {
+            name: String::from(name),
+            builder,
+            texture: Rc::new(texture),
+            animation,
+        }
+    }
+
+    /**
+    Re-upload the texture from its image, and restart its animation.
+    If the image (or its sidecar) can't be loaded, the current texture is kept and the error is returned.
+    Handles to the old texture stay valid, but aren't updated.
+    */
+    pub fn reload(&mut self, res: &Resources) -> Result<(), Error> {
+        let (image, animation) = animation::load_animated(res, &self.name)?;
+        self.texture = Rc::new(self.builder.build(&image, &self.name)?);
+        self.animation = animation;
+        Ok(())
+    }
+
+    /**
+    Move the animation (if there is one) on by `delta_ms` milliseconds, updating the texture.
+    If updating fails the animation is stopped, and the error is returned.
+    */
+    pub fn update_animation(&mut self, delta_ms: u32) -> Result<(), Error> {
+        if let Some(animation) = &mut self.animation {
+            if let Err(e) = animation.update_texture(delta_ms, &self.texture, &self.builder) {
+                self.animation = None;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    // Whether the texture was loaded from the given resource, or it's the image's animation sidecar
+    pub fn depends_on(&self, resource_name: &str) -> bool {
+        self.name == resource_name || resource_name == self.sidecar_name()
+    }
+
+    // Name of the sidecar that would make the image animated (see `texture::animation`), whether or not it exists
+    pub fn sidecar_name(&self) -> String {
+        format!("{}{}", self.name, animation::SIDECAR_EXT)
+    }
+
+    pub fn resource_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn builder(&self) -> &TextureBuilder {
+        &self.builder
+    }
+
+    // A shared handle to the current texture
+    pub fn handle(&self) -> Handle<Texture> {
+        Rc::clone(&self.texture)
+    }
+}
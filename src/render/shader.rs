@@ -3,49 +3,168 @@ extern crate gl;
 use std::ffi::{CString, CStr};
 use crate::ffi_utils;
 use crate::resources::Resources;
+use crate::render::Error;
+use crate::render::preprocess::{self, Preprocessed};
+use crate::render::diagnostics;
+
+// Shader stages: the file extension for a shader of just that stage, the name of its `#stage` marker, and its GL type
+const STAGES: [(&str, &str, gl::types::GLenum); 4] = [
+    (".vert", "vertex", gl::VERTEX_SHADER),
+    (".geom", "geometry", gl::GEOMETRY_SHADER),
+    (".frag", "fragment", gl::FRAGMENT_SHADER),
+    (".comp", "compute", gl::COMPUTE_SHADER),
+];
+
+// Extension of shaders containing several stages, split by `#stage` markers
+pub const MULTI_STAGE_EXT: &str = ".glsl";
+
+/**
+A shader stage that's been loaded and preprocessed, ready to compile.
+This doesn't need the GL context, so can be done on any thread.
+*/
+pub struct Stage {
+    pub shader_type: gl::types::GLenum,
+    pub marker: Option<&'static str>,   // The `#stage` it was split out by, if it came from a multi-stage file
+    pub preprocessed: Preprocessed,
+}
+
+/**
+Load and preprocess the stages in a shader resource: either a single stage file (`.vert`, `.geom`, `.frag`, `.comp`),
+or a `.glsl` file with several stages. eg/
+```glsl
+#version 330 core
+// Anything here is shared by every stage
+#stage vertex
+void main() { ... }
+#stage fragment
+void main() { ... }
+```
+*/
+pub fn load_stages(res: &Resources, name: &str, defines: &[(&str, &str)]) -> Result<Vec<Stage>, Error> {
+    if !name.ends_with(MULTI_STAGE_EXT) {
+        return Ok(vec![Stage {
+            shader_type: Shader::type_from_name(name)?,
+            marker: None,
+            preprocessed: preprocess::preprocess(res, name, defines)?,
+        }]);
+    }
+
+    let source = preprocess::load(res, name)?;
+    let stages = preprocess::split_stages(&source);
+    if stages.is_empty() {
+        return Err(Error::Preprocess { name: String::from(name), line: 1, message: String::from("No `#stage` markers found") });
+    }
+
+    stages.into_iter()
+        .map(|(marker, line, stage_source)| {
+            let &(_, marker, shader_type) = STAGES.iter()
+                .find(|&&(_, stage_marker, _)| stage_marker == marker)
+                .ok_or_else(|| Error::Preprocess {
+                    name: String::from(name),
+                    line,
+                    message: format!("Unknown stage `{}`", marker),
+                })?;
+
+            Ok(Stage {
+                shader_type,
+                marker: Some(marker),
+                preprocessed: preprocess::preprocess_source(res, name, &stage_source, defines)?,
+            })
+        })
+        .collect()
+}
 
 pub struct Shader {
     id: gl::types::GLuint,
+    name: String,   // Where the shader came from, for error reporting
+    files: Vec<String>,     // Every resource the source was built from (the shader, then its includes)
 }
 
 impl Shader {
-    fn from_source(
+    // Compile a shader from source. `name` is only used for error reporting.
+    pub fn from_source(
         source: &CStr,
-        shader_type: gl::types::GLenum
-    ) -> Result<Shader, String> {
-        let id = shader_from_source(source, shader_type)?;
+        shader_type: gl::types::GLenum,
+        name: &str
+    ) -> Result<Shader, Error> {
+        Shader::compile(source, shader_type, name, vec![String::from(name)])
+    }
 
-        Ok(Shader { id })
+    // Compile a preprocessed shader. The shader is named after the file it was preprocessed from.
+    pub fn from_preprocessed(preprocessed: &Preprocessed, shader_type: gl::types::GLenum) -> Result<Shader, Error> {
+        let name = preprocessed.files[0].clone();
+        Shader::compile(&preprocessed.source, shader_type, &name, preprocessed.files.clone())
     }
 
-    pub fn from_vert_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::VERTEX_SHADER)
+    // Compile a loaded stage
+    pub fn from_stage(stage: &Stage) -> Result<Shader, Error> {
+        Shader::from_preprocessed(&stage.preprocessed, stage.shader_type)
+            .map_err(|e| match (e, stage.marker) {
+                // Say which of the file's stages failed
+                (Error::Compile { name, log, diagnostics }, Some(marker)) => {
+                    Error::Compile { name: format!("{} ({} stage)", name, marker), log, diagnostics }
+                },
+                (e, _) => e,
+            })
     }
 
-    pub fn from_frag_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::FRAGMENT_SHADER)
+    fn compile(source: &CStr, shader_type: gl::types::GLenum, name: &str, files: Vec<String>) -> Result<Shader, Error> {
+        if shader_type == gl::COMPUTE_SHADER && !gl::DispatchCompute::is_loaded() {
+            return Err(Error::Unsupported { name: String::from(name), feature: "compute shaders" });
+        }
+
+        let id = match shader_from_source(source, shader_type) {
+            Ok(id) => id,
+            Err(log) => {
+                let diagnostics = diagnostics::parse_log(&log, &source.to_string_lossy(), &files);
+                return Err(Error::Compile { name: String::from(name), log, diagnostics });
+            },
+        };
+
+        Ok(Shader { id, name: String::from(name), files })
     }
 
-    // Load a shader from a resource
-    pub fn from_resource(res: &Resources, name: &str) -> Result<Shader, String> {
-        // Define possible types from extensions
-        const POSSIBLE_EXT: [(&str, gl::types::GLenum); 2] = [
-            (".vert", gl::VERTEX_SHADER),
-            (".frag", gl::FRAGMENT_SHADER),
-        ];
+    pub fn from_vert_source(source: &CStr) -> Result<Shader, Error> {
+        Shader::from_source(source, gl::VERTEX_SHADER, "<vertex source>")
+    }
 
-        // Determine shader type
-        let shader_type = POSSIBLE_EXT.iter()
-            .find(|&&(file_extension, _)| {
-                name.ends_with(file_extension)
-            })
-            .map(|&(_, kind)| kind)
-            .ok_or_else(|| format!("Can not determine shader type for resource {}", name))?;
+    pub fn from_frag_source(source: &CStr) -> Result<Shader, Error> {
+        Shader::from_source(source, gl::FRAGMENT_SHADER, "<fragment source>")
+    }
+
+    // Load a single stage shader from a resource, resolving any `#include`s
+    pub fn from_resource(res: &Resources, name: &str) -> Result<Shader, Error> {
+        Shader::from_resource_with_defines(res, name, &[])
+    }
+
+    // Load a single stage shader from a resource, with extra `#define`s. `defines` are `(name, value)` pairs.
+    pub fn from_resource_with_defines(res: &Resources, name: &str, defines: &[(&str, &str)]) -> Result<Shader, Error> {
+        let shader_type = Shader::type_from_name(name)?;
+        let preprocessed = preprocess::preprocess(res, name, defines)?;
 
-        let source = res.load_cstring(name)
-            .map_err(|e| format!("Error loading resource {}: {:?}", name, e))?;
+        Shader::from_preprocessed(&preprocessed, shader_type)
+    }
+
+    // Load every stage from some shader resources (which may be single or multi-stage files)
+    pub fn all_from_resources<S: AsRef<str>>(res: &Resources, names: &[S], defines: &[(&str, &str)]) -> Result<Vec<Shader>, Error> {
+        let mut shaders = Vec::new();
+        for name in names {
+            for stage in load_stages(res, name.as_ref(), defines)? {
+                shaders.push(Shader::from_stage(&stage)?);
+            }
+        }
 
-        Shader::from_source(&source, shader_type)
+        Ok(shaders)
+    }
+
+    // Determine the shader type from a resource's extension
+    pub fn type_from_name(name: &str) -> Result<gl::types::GLenum, Error> {
+        STAGES.iter()
+            .find(|&&(file_extension, _, _)| {
+                name.ends_with(file_extension)
+            })
+            .map(|&(_, _, kind)| kind)
+            .ok_or_else(|| Error::UnknownShaderType { name: String::from(name) })
     }
 
     // Getter for own ID
@@ -53,6 +172,15 @@ impl Shader {
         self.id
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // The resources the shader was built from, so it can be rebuilt when any of them change
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
 }
 
 impl Drop for Shader {
@@ -0,0 +1,253 @@
+/*
+Animated textures (eg/ water, lava and fire): flipbooks of equally sized frames stacked top to bottom in one image,
+played as a sidecar file next to the image says. Sidecars are named `<image>.mcmeta` (eg/ `textures/water.png.mcmeta`),
+and use the same format as Minecraft's:
+```json
+{
+    "animation": {
+        "frametime": 2,
+        "interpolate": true,
+        "frames": [0, 1, 2, { "index": 3, "time": 10 }],
+        "width": 16,
+        "height": 16
+    }
+}
+```
+- `frametime`: game ticks (see `TICK_MS`) each frame is shown for, unless the frame says otherwise. Defaults to 1.
+- `interpolate`: blend smoothly into the next frame, rather than switching. Defaults to false.
+- `frames`: the order to show frames in, by index from the top of the image. Defaults to every frame, in order.
+- `width` and `height`: size of a frame. Defaults to square frames, as wide as the image.
+Images without a sidecar aren't animated.
+
+Animations are played into a texture, an atlas image or a texture array layer (which should be created from the
+first frame, see `load_first_frame`), by calling `update_texture`, `update_atlas` or `update_array` each frame
+with the time since the last one. The game does this for every texture in the manifest.
+*/
+
+use std::convert::TryInto;
+
+use serde::Deserialize;
+
+use crate::resources::Resources;
+use crate::resources::decode::{DecodedImage, PixelFormat};
+use crate::render::Error;
+use super::{Texture, TextureBuilder, Atlas, TextureArray};
+
+// Length of a game tick, which frame times are measured in
+pub const TICK_MS: u32 = 50;
+// Added to an image's name to get its sidecar's name
+pub const SIDECAR_EXT: &str = ".mcmeta";
+
+#[derive(Deserialize)]
+struct Sidecar {
+    animation: AnimationMeta,
+}
+
+#[derive(Deserialize)]
+struct AnimationMeta {
+    #[serde(default = "default_frametime")]
+    frametime: u32,
+    #[serde(default)]
+    interpolate: bool,
+    frames: Option<Vec<FrameMeta>>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+fn default_frametime() -> u32 {
+    1
+}
+
+// A frame in the sequence: just an index, or an object with an index and (optionally) its own time
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameMeta {
+    Index(usize),
+    Timed { index: usize, time: Option<u32> },
+}
+
+pub struct Animation {
+    name: String,
+    frames: Vec<DecodedImage>,      // 8 bit RGBA, from the top of the image down
+    sequence: Vec<(usize, u32)>,    // Frame to show, and for how many milliseconds
+    interpolate: bool,
+    step: usize,    // Current position in `sequence`
+    elapsed: u32,   // Milliseconds the current step has been shown for
+}
+
+impl Animation {
+    /**
+    Load an image resource and its sidecar. Returns `None` if there's no sidecar, as the image isn't animated.
+    */
+    pub fn from_resource(res: &Resources, name: &str) -> Result<Option<Animation>, Error> {
+        let sidecar_name = format!("{}{}", name, SIDECAR_EXT);
+        if res.provider_of(&sidecar_name).is_none() {
+            return Ok(None);
+        }
+
+        let sidecar = res.load_bytes(&sidecar_name)
+            .map_err(|e| Error::Resource { name: sidecar_name.clone(), inner: e })?;
+        let image = res.load_image(name)
+            .map_err(|e| Error::Resource { name: String::from(name), inner: e })?;
+
+        Animation::from_image(name, &image, &sidecar).map(Some)
+    }
+
+    // Create an animation from a flipbook image and the contents of its sidecar. `name` is only used for error reporting.
+    pub fn from_image(name: &str, image: &DecodedImage, sidecar: &[u8]) -> Result<Animation, Error> {
+        let invalid = |message: String| Error::Animation { name: String::from(name), message };
+
+        let meta = serde_json::from_slice::<Sidecar>(sidecar)
+            .map_err(|e| invalid(e.to_string()))?
+            .animation;
+
+        let frame_width = meta.width.unwrap_or(image.width);
+        let frame_height = meta.height.unwrap_or(frame_width);
+        if frame_width == 0 || frame_height == 0 || frame_width > image.width || frame_height > image.height {
+            return Err(invalid(format!(
+                "Frames are {}x{}, which doesn't fit in the {}x{} image", frame_width, frame_height, image.width, image.height
+            )));
+        }
+
+        let image = image.to_rgba8();
+        let frames: Vec<DecodedImage> = (0..image.height / frame_height)
+            .map(|frame| crop(&image, frame * frame_height, frame_width, frame_height))
+            .collect();
+
+        // Times are at least a millisecond, so the animation always moves on
+        let to_ms = |ticks: u32| ticks.saturating_mul(TICK_MS).max(1);
+        let sequence: Vec<(usize, u32)> = match &meta.frames {
+            Some(frame_metas) => frame_metas.iter()
+                .map(|frame_meta| match *frame_meta {
+                    FrameMeta::Index(index) => (index, to_ms(meta.frametime)),
+                    FrameMeta::Timed { index, time } => (index, to_ms(time.unwrap_or(meta.frametime))),
+                })
+                .collect(),
+            None => (0..frames.len()).map(|index| (index, to_ms(meta.frametime))).collect(),
+        };
+        if sequence.is_empty() {
+            return Err(invalid(String::from("There are no frames")));
+        }
+        if let Some(&(index, _)) = sequence.iter().find(|&&(index, _)| index >= frames.len()) {
+            return Err(invalid(format!("Frame {} doesn't exist, the image only has {}", index, frames.len())));
+        }
+
+        Ok(Animation {
+            name: String::from(name),
+            frames,
+            sequence,
+            interpolate: meta.interpolate,
+            step: 0,
+            elapsed: 0,
+        })
+    }
+
+    /**
+    Move the animation on by `delta_ms` milliseconds (eg/ the main loop's tick delta).
+    Returns whether the image to show has changed.
+    */
+    pub fn advance(&mut self, delta_ms: u32) -> bool {
+        if delta_ms == 0 {
+            return false;
+        }
+
+        // Skip any whole loops (eg/ after the game was paused)
+        let loop_ms: u32 = self.sequence.iter().map(|&(_, time)| time).fold(0, u32::saturating_add);
+        let start_step = self.step;
+        self.elapsed = self.elapsed.saturating_add(delta_ms % loop_ms);
+        while self.elapsed >= self.sequence[self.step].1 {
+            self.elapsed -= self.sequence[self.step].1;
+            self.step = (self.step + 1) % self.sequence.len();
+        }
+
+        self.interpolate || self.sequence[self.step].0 != self.sequence[start_step].0
+    }
+
+    // The image to show now: the current frame, blended into the next if the animation interpolates
+    pub fn image(&self) -> DecodedImage {
+        let (frame, time) = self.sequence[self.step];
+        let current = &self.frames[frame];
+        if !self.interpolate {
+            return current.clone();
+        }
+
+        let next = &self.frames[self.sequence[(self.step + 1) % self.sequence.len()].0];
+        let amount = self.elapsed as f32 / time as f32;
+        let data = current.data.iter().zip(next.data.iter())
+            .map(|(&from, &to)| (f32::from(from) + (f32::from(to) - f32::from(from)) * amount).round() as u8)
+            .collect();
+
+        DecodedImage { width: current.width, height: current.height, format: current.format, data }
+    }
+
+    // Advance the animation, and show it in a texture, regenerating its mipmaps if `builder` has them
+    pub fn update_texture(&mut self, delta_ms: u32, texture: &Texture, builder: &TextureBuilder) -> Result<(), Error> {
+        if self.advance(delta_ms) {
+            texture.update_image(&self.image(), &self.name)?;
+            if builder.has_mipmaps() {
+                builder.configure(texture);
+            }
+        }
+        Ok(())
+    }
+
+    // Advance the animation, and show it in the atlas image with the same name
+    pub fn update_atlas(&mut self, delta_ms: u32, atlas: &Atlas) -> Result<(), Error> {
+        if self.advance(delta_ms) {
+            atlas.update_image(&self.name, &self.image())?;
+        }
+        Ok(())
+    }
+
+    // Advance the animation, and show it in the texture array layer with the same name
+    pub fn update_array(&mut self, delta_ms: u32, array: &TextureArray) -> Result<(), Error> {
+        if self.advance(delta_ms) {
+            let layer = array.layer(&self.name)
+                .ok_or_else(|| Error::Animation { name: self.name.clone(), message: String::from("Not in the texture array") })?;
+            array.update_layer(layer, &self.image(), &self.name)?;
+        }
+        Ok(())
+    }
+
+    // The image resource the animation was loaded from
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Size of each frame in pixels
+    pub fn frame_size(&self) -> (u32, u32) {
+        (self.frames[0].width, self.frames[0].height)
+    }
+}
+
+/**
+Load an image resource as a texture should start out: the first frame shown, if it's animated, otherwise the whole image.
+*/
+pub fn load_first_frame(res: &Resources, name: &str) -> Result<DecodedImage, Error> {
+    load_animated(res, name).map(|(image, _)| image)
+}
+
+/**
+Load an image resource as a texture should start out (see `load_first_frame`), along with its animation if it has one.
+The image is only decoded once.
+*/
+pub fn load_animated(res: &Resources, name: &str) -> Result<(DecodedImage, Option<Animation>), Error> {
+    match Animation::from_resource(res, name)? {
+        Some(animation) => Ok((animation.image(), Some(animation))),
+        None => res.load_image(name)
+            .map(|image| (image, None))
+            .map_err(|e| Error::Resource { name: String::from(name), inner: e }),
+    }
+}
+
+// Copy a `width` x `height` area from the left of an RGBA8 image, starting at row `top`
+fn crop(image: &DecodedImage, top: u32, width: u32, height: u32) -> DecodedImage {
+    let row_len: usize = (image.width * 4).try_into().unwrap();
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in top..top + height {
+        let start = row as usize * row_len;
+        data.extend_from_slice(&image.data[start..start + width as usize * 4]);
+    }
+
+    DecodedImage { width, height, format: PixelFormat::RGBA8, data }
+}
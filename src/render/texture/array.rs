@@ -0,0 +1,180 @@
+/*
+Texture arrays (`GL_TEXTURE_2D_ARRAY`): a stack of equally sized images ("layers") in one texture.
+Shaders read them with a `sampler2DArray`, picking the layer with the third texture coordinate
+(see `VertexPTL`), eg/ `texture(blocks, vec3(texture_coords, layer))`.
+
+Unlike an atlas, each layer is filtered, wrapped and mipmapped on its own, so block faces can repeat and
+mipmap without picking up their neighbours.
+*/
+
+use std::convert::TryInto;
+
+use crate::resources::Resources;
+use crate::resources::decode::{DecodedImage, PixelFormat};
+use crate::render::Error;
+use super::{animation, TextureBuilder, Upload};
+
+pub struct TextureArray {
+    id: gl::types::GLuint,
+    width: u32,
+    height: u32,
+    layers: Vec<String>,    // Name of each layer's image, in order
+    builder: TextureBuilder,    // Used again when layers are updated, to regenerate their mipmaps
+}
+
+impl TextureArray {
+    // Load each resource as a layer, in order. Animated images start on their first frame.
+    pub fn from_resources<S: AsRef<str>>(res: &Resources, names: &[S], builder: &TextureBuilder) -> Result<TextureArray, Error> {
+        let images = names.iter()
+            .map(|name| {
+                let name = name.as_ref();
+                animation::load_first_frame(res, name).map(|image| (String::from(name), image))
+            })
+            .collect::<Result<Vec<(String, DecodedImage)>, Error>>()?;
+
+        TextureArray::from_images(&images, builder)
+    }
+
+    /**
+    Create an array from (name, image) layers, which must all be the same size. Layers are stored as 8 bit RGBA
+    (sRGB if the builder asks for it), and each gets its own mipmaps if the builder has them.
+    */
+    pub fn from_images(images: &[(String, DecodedImage)], builder: &TextureBuilder) -> Result<TextureArray, Error> {
+        let (width, height) = images.first()
+            .map(|(_, image)| (image.width, image.height))
+            .unwrap_or((1, 1));
+        let upload = Upload::of(PixelFormat::RGBA8, builder.colour_space());
+
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            // Allocate every layer, then fill them in
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                upload.internal_format as gl::types::GLint,
+                gl_size(width),
+                gl_size(height),
+                gl_size(images.len() as u32),
+                0,
+                upload.format,
+                upload.data_type,
+                std::ptr::null()
+            );
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAX_LEVEL, 0);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+        // Deletes the texture if a layer is rejected
+        let mut array = TextureArray {
+            id,
+            width,
+            height,
+            layers: Vec::with_capacity(images.len()),
+            builder: builder.clone(),
+        };
+
+        for (layer, (name, image)) in images.iter().enumerate() {
+            array.upload_layer(layer as u32, images.len(), image, name)?;
+            array.layers.push(name.clone());
+        }
+
+        builder.apply(gl::TEXTURE_2D_ARRAY, array.id, true);
+
+        Ok(array)
+    }
+
+    /**
+    Replace the image of a layer (eg/ for the next frame of an animation), which must be the same size as the others.
+    Its mipmaps are regenerated if it has them. `name` is only used for error reporting.
+    */
+    pub fn update_layer(&self, layer: u32, image: &DecodedImage, name: &str) -> Result<(), Error> {
+        self.upload_layer(layer, self.layers.len(), image, name)?;
+        if self.builder.has_mipmaps() {
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+                gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+                gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+            }
+        }
+        Ok(())
+    }
+
+    // Copy an image into the full size level of a layer, out of `layer_count` allocated
+    fn upload_layer(&self, layer: u32, layer_count: usize, image: &DecodedImage, name: &str) -> Result<(), Error> {
+        if layer as usize >= layer_count {
+            return Err(Error::LayerOutOfRange { name: String::from(name), layer, layer_count });
+        }
+        if (image.width, image.height) != (self.width, self.height) {
+            return Err(Error::LayerSize {
+                name: String::from(name),
+                expected: (self.width, self.height),
+                found: (image.width, image.height),
+            });
+        }
+
+        let converted;
+        let image = if image.format == PixelFormat::RGBA8 {
+            image
+        } else {
+            converted = image.to_rgba8();
+            &converted
+        };
+        let upload = Upload::of(PixelFormat::RGBA8, self.builder.colour_space());
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+            gl::TexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                0, 0, gl_size(layer),   // Offset
+                gl_size(self.width), gl_size(self.height), 1,
+                upload.format,
+                upload.data_type,
+                image.data.as_ptr() as *const std::ffi::c_void
+            );
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+
+        Ok(())
+    }
+
+    // The layer an image was loaded into
+    pub fn layer(&self, name: &str) -> Option<u32> {
+        self.layers.iter().position(|layer| layer == name).map(|layer| layer as u32)
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    // Size of each layer in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Bind the array to a texture unit, for a `sampler2DArray` uniform set to that unit to read
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+}
+
+impl Drop for TextureArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+// Sizes are checked against the driver's limits by the driver itself, so just keep them in range here
+fn gl_size(size: u32) -> gl::types::GLsizei {
+    size.try_into().unwrap_or(gl::types::GLsizei::MAX)
+}
@@ -0,0 +1,342 @@
+/*
+Texture atlases: many small images (eg/ block faces) packed into one texture, so they can all be drawn together.
+
+Each image gets a rectangle of the atlas, looked up by name as a `UvRect`. Meshes map their 0 -> 1 texture
+coordinates into that rectangle (see `UvRect::map`), so they can't use `Wrap::Repeat` to tile a face.
+Images are surrounded by padding filled with copies of their edge pixels, so linear filtering (and small UV
+errors) at the edge of an image pick up its own colour rather than its neighbour's.
+*/
+
+use std::collections::HashMap;
+
+use crate::resources::{self, Resources};
+use crate::resources::decode::{DecodedImage, PixelFormat};
+use crate::obj::vertex::Coords2D;
+use crate::render::Error;
+use super::{animation, Texture, TextureBuilder, Filter, Wrap};
+
+// The area of an atlas an image was packed into, in texture coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+    pub u_min: f32,
+    pub v_min: f32,
+    pub u_max: f32,
+    pub v_max: f32,
+}
+
+impl UvRect {
+    // Map texture coordinates for the image on its own (0 -> 1) to coordinates in the atlas
+    pub fn map(&self, coords: Coords2D) -> Coords2D {
+        Coords2D {
+            x: self.u_min + coords.x * (self.u_max - self.u_min),
+            y: self.v_min + coords.y * (self.v_max - self.v_min),
+        }
+    }
+}
+
+pub struct AtlasBuilder {
+    padding: u32,   // Pixels of edge copies around each image
+    images: Vec<(String, DecodedImage)>,
+}
+
+impl AtlasBuilder {
+    /**
+    Start an atlas, with `padding` pixels around each image. 1 is enough for linear filtering without mipmaps.
+    With mipmaps, padding shrinks by half at each level, so linearly filtered atlases only get as many levels as
+    the padding allows (eg/ 4 pixels of padding allows 3 levels below the full size image).
+    */
+    pub fn new(padding: u32) -> AtlasBuilder {
+        AtlasBuilder {
+            padding,
+            images: Vec::new(),
+        }
+    }
+
+    // Add an image. Adding another image with the same name replaces it.
+    pub fn add_image(&mut self, name: &str, image: &DecodedImage) {
+        let image = image.to_rgba8();
+        match self.images.iter_mut().find(|(image_name, _)| image_name == name) {
+            Some((_, current)) => *current = image,
+            None => self.images.push((String::from(name), image)),
+        }
+    }
+
+    // Add an image resource, under its resource name. Only the first frame of animated images is added.
+    pub fn add_resource(&mut self, res: &Resources, name: &str) -> Result<(), Error> {
+        let image = animation::load_first_frame(res, name)?;
+        self.add_image(name, &image);
+        Ok(())
+    }
+
+    /**
+    Pack the images into a texture (with a GL context current), sampled as `settings` says. The atlas is as small
+    a power of two as they fit in, up to the driver's maximum texture size. `name` is only used for error reporting.
+
+    Mipmaps are made here rather than by the driver, so they never mix neighbouring images: every image's area
+    is aligned to a multiple of `2^levels` pixels, so each mip pixel only covers one image. The number of levels
+    is limited by the smallest image, `settings`' max mip level and, with a linear min filter, the padding.
+    */
+    pub fn build(&self, name: &str, settings: &TextureBuilder) -> Result<Atlas, Error> {
+        let mut max_size: gl::types::GLint = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size); }
+        let max_size = max_size.max(1) as u32;
+
+        let mip_levels = if settings.has_mipmaps() {
+            let smallest = self.images.iter()
+                .map(|(_, image)| image.width.min(image.height))
+                .min()
+                .unwrap_or(1)
+                .max(1);
+            // Until the smallest image is a single pixel
+            let mut levels = (31 - smallest.leading_zeros()).min(settings.max_mip_level());
+
+            // Linear filtering reads half a pixel past an image's edge, so every level needs at least that much
+            // padding: `padding / 2^level >= 1/2`, ie/ `level <= log2(padding) + 1`
+            if settings.min_filter() == Filter::Linear {
+                levels = levels.min(32 - self.padding.leading_zeros());
+            }
+            levels
+        } else {
+            0
+        };
+
+        let packed = self.pack(max_size, 1 << mip_levels)
+            .ok_or_else(|| Error::AtlasTooLarge { name: String::from(name), max_size })?;
+
+        let mut levels = vec![packed.image];
+        for _ in 0..mip_levels {
+            let next = half_size(levels.last().unwrap());
+            levels.push(next);
+        }
+
+        let texture = Texture::from_levels(&levels, name, settings.colour_space())?;
+        // Coordinates outside the atlas would read other images, so don't wrap
+        settings.clone().with_wrap(Wrap::ClampToEdge).set_parameters(&texture);
+
+        Ok(Atlas {
+            texture,
+            width: levels[0].width,
+            height: levels[0].height,
+            padding: self.padding,
+            mip_levels,
+            uvs: packed.uvs,
+            cells: packed.cells,
+        })
+    }
+
+    /**
+    Pack the images into a single image, with each image's area (including padding) a multiple of `alignment` pixels
+    in each direction. Returns `None` if they don't fit in `max_size` x `max_size`.
+    */
+    fn pack(&self, max_size: u32, alignment: u32) -> Option<Packed> {
+        let align = |size: u32| size.div_ceil(alignment) * alignment;
+        let cells: Vec<(u32, u32)> = self.images.iter()
+            .map(|(_, image)| (align(image.width + 2 * self.padding), align(image.height + 2 * self.padding)))
+            .collect();
+
+        // Start from the smallest square that could hold everything, and widen it until it does
+        let area: u64 = cells.iter().map(|&(width, height)| u64::from(width) * u64::from(height)).sum();
+        let widest = cells.iter().map(|&(width, _)| width).max().unwrap_or(1);
+        let mut width = widest.max((area as f64).sqrt().ceil() as u32).max(1).next_power_of_two();
+        let (positions, height) = loop {
+            if width > max_size {
+                return None;
+            }
+            let (positions, used_height) = shelf_pack(&cells, width);
+            let height = used_height.max(1).next_power_of_two();
+            if height <= width {
+                break (positions, height);
+            }
+            width *= 2;
+        };
+
+        let mut data = vec![0u8; width as usize * height as usize * 4];
+        let mut uvs = HashMap::new();
+        let mut placed_cells = HashMap::new();
+        for (((name, image), &(x, y)), &cell) in self.images.iter().zip(positions.iter()).zip(cells.iter()) {
+            copy_extruded(image, &mut data, width, (x, y), cell, self.padding);
+            placed_cells.insert(name.clone(), Cell { x, y, width: cell.0, height: cell.1, image_width: image.width, image_height: image.height });
+
+            let (left, top) = (x + self.padding, y + self.padding);
+            uvs.insert(name.clone(), UvRect {
+                u_min: left as f32 / width as f32,
+                v_min: top as f32 / height as f32,
+                u_max: (left + image.width) as f32 / width as f32,
+                v_max: (top + image.height) as f32 / height as f32,
+            });
+        }
+
+        Some(Packed {
+            image: DecodedImage { width, height, format: PixelFormat::RGBA8, data },
+            uvs,
+            cells: placed_cells,
+        })
+    }
+}
+
+// The area an image (and its padding) was packed into, in pixels
+#[derive(Copy, Clone, Debug)]
+struct Cell {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    image_width: u32,
+    image_height: u32,
+}
+
+// The images packed into one, before it's uploaded
+struct Packed {
+    image: DecodedImage,
+    uvs: HashMap<String, UvRect>,
+    cells: HashMap<String, Cell>,
+}
+
+pub struct Atlas {
+    texture: Texture,
+    width: u32,
+    height: u32,
+    padding: u32,
+    mip_levels: u32,    // Number of levels after the full size one
+    uvs: HashMap<String, UvRect>,   // Image name -> where it is in the atlas
+    cells: HashMap<String, Cell>,
+}
+
+impl Atlas {
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    // Size of the atlas in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Where an image is in the atlas
+    pub fn uv(&self, name: &str) -> Option<UvRect> {
+        self.uvs.get(name).copied()
+    }
+
+    // Every image in the atlas, and where it is
+    pub fn uvs(&self) -> &HashMap<String, UvRect> {
+        &self.uvs
+    }
+
+    /**
+    Replace an image in the atlas (eg/ with the next frame of an animation), along with its padding and mip levels.
+    The new image must be the same size as the old one.
+    */
+    pub fn update_image(&self, name: &str, image: &DecodedImage) -> Result<(), Error> {
+        let cell = match self.cells.get(name) {
+            Some(cell) => *cell,
+            None => return Err(Error::Resource { name: String::from(name), inner: resources::Error::NotFound(String::from(name)) }),
+        };
+        if (image.width, image.height) != (cell.image_width, cell.image_height) {
+            return Err(Error::LayerSize {
+                name: String::from(name),
+                expected: (cell.image_width, cell.image_height),
+                found: (image.width, image.height),
+            });
+        }
+
+        let mut data = vec![0u8; cell.width as usize * cell.height as usize * 4];
+        copy_extruded(&image.to_rgba8(), &mut data, cell.width, (0, 0), (cell.width, cell.height), self.padding);
+        let mut level_image = DecodedImage { width: cell.width, height: cell.height, format: PixelFormat::RGBA8, data };
+
+        unsafe { gl::BindTexture(gl::TEXTURE_2D, self.texture.id()); }
+        for level in 0..=self.mip_levels {
+            // Cells are aligned to `2^mip_levels` pixels, so each level of the cell starts on a whole pixel
+            unsafe {
+                gl::TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    level as gl::types::GLint,
+                    (cell.x >> level) as gl::types::GLint,
+                    (cell.y >> level) as gl::types::GLint,
+                    level_image.width as gl::types::GLsizei,
+                    level_image.height as gl::types::GLsizei,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    level_image.data.as_ptr() as *const std::ffi::c_void
+                );
+            }
+            if level < self.mip_levels {
+                level_image = half_size(&level_image);
+            }
+        }
+        unsafe { gl::BindTexture(gl::TEXTURE_2D, 0); }
+
+        Ok(())
+    }
+}
+
+/**
+Place rectangles in rows ("shelves") across a `width` wide area, tallest first so each row wastes little space.
+Returns the top left corner of each rectangle (in the order given) and the height used.
+*/
+fn shelf_pack(sizes: &[(u32, u32)], width: u32) -> (Vec<(u32, u32)>, u32) {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| (std::cmp::Reverse(sizes[index].1), std::cmp::Reverse(sizes[index].0)));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for index in order {
+        let (rect_width, rect_height) = sizes[index];
+        if x + rect_width > width {
+            // Start a new shelf
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+
+        positions[index] = (x, y);
+        x += rect_width;
+        shelf_height = shelf_height.max(rect_height);
+    }
+
+    (positions, y + shelf_height)
+}
+
+/**
+Copy an RGBA8 image into the `cell` sized area of the atlas at `corner`, `padding` pixels in from its top left.
+The rest of the cell is filled with copies of the image's edges.
+*/
+fn copy_extruded(image: &DecodedImage, atlas: &mut [u8], atlas_width: u32, corner: (u32, u32), cell: (u32, u32), padding: u32) {
+    if image.width == 0 || image.height == 0 {
+        return;
+    }
+
+    let (x, y) = corner;
+    let (cell_width, cell_height) = cell;
+    for row in 0..cell_height {
+        let source_row = row.saturating_sub(padding).min(image.height - 1);
+        for column in 0..cell_width {
+            let source_column = column.saturating_sub(padding).min(image.width - 1);
+
+            let source = ((source_row * image.width + source_column) * 4) as usize;
+            let target = (((y + row) * atlas_width + x + column) * 4) as usize;
+            atlas[target..target + 4].copy_from_slice(&image.data[source..source + 4]);
+        }
+    }
+}
+
+// Halve the size of an RGBA8 image (with even width and height), averaging each 2x2 block of pixels
+fn half_size(image: &DecodedImage) -> DecodedImage {
+    let (width, height) = ((image.width / 2).max(1), (image.height / 2).max(1));
+    let pixel = |x: u32, y: u32, channel: u32| -> u32 {
+        let (x, y) = (x.min(image.width - 1), y.min(image.height - 1));
+        u32::from(image.data[((y * image.width + x) * 4 + channel) as usize])
+    };
+
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            for channel in 0..4 {
+                let sum = pixel(2 * x, 2 * y, channel) + pixel(2 * x + 1, 2 * y, channel)
+                    + pixel(2 * x, 2 * y + 1, channel) + pixel(2 * x + 1, 2 * y + 1, channel);
+                data.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+
+    DecodedImage { width, height, format: PixelFormat::RGBA8, data }
+}
@@ -0,0 +1,236 @@
+/*
+Settings for creating a texture: how it's sampled, whether it has mipmaps, and how its colours are interpreted.
+Keeping them together means a texture can be rebuilt (eg/ when it's hot reloaded) exactly as it was first made.
+
+eg/ a block texture that stays crisp up close, but doesn't shimmer in the distance:
+```
+let builder = TextureBuilder::new()
+    .with_filter(Filter::Nearest)
+    .with_mipmaps(Some(Filter::Linear))
+    .with_anisotropy(8.0)
+    .with_colour_space(ColourSpace::Srgb);
+let texture = builder.build_from_resource(&res, "textures/stone.png")?;
+```
+*/
+
+use std::cell::Cell;
+use std::ffi::CStr;
+
+use crate::resources::Resources;
+use crate::resources::decode::DecodedImage;
+use crate::render::Error;
+use super::{Texture, Filter, Wrap, ColourSpace};
+
+// Extensions that add `TEXTURE_MAX_ANISOTROPY` (it's core from OpenGL 4.6)
+const ANISOTROPY_EXTENSIONS: [&str; 2] = ["GL_ARB_texture_filter_anisotropic", "GL_EXT_texture_filter_anisotropic"];
+// The `gl` crate is generated for 4.5, so doesn't have these
+const TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FF;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureBuilder {
+    min_filter: Filter,         // Used when the texture is shrunk
+    mag_filter: Filter,         // Used when the texture is enlarged
+    mipmap_filter: Option<Filter>,  // How to blend between mip levels, or `None` for no mipmaps
+    max_mip_level: u32,
+    wrap_s: Wrap,
+    wrap_t: Wrap,
+    anisotropy: f32,    // 1 is off
+    lod_bias: f32,      // Added to the mip level the driver picks: positive is blurrier, negative sharper
+    colour_space: ColourSpace,
+}
+
+impl TextureBuilder {
+    // Nearest filtering, repeating, no mipmaps, linear colours: the same as `Texture::from_image`
+    pub fn new() -> TextureBuilder {
+        TextureBuilder {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: None,
+            max_mip_level: 1000,    // OpenGL's default: as many levels as the texture's size allows
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            anisotropy: 1.0,
+            lod_bias: 0.0,
+            colour_space: ColourSpace::Linear,
+        }
+    }
+
+    // -- Settings -- //
+    // Set both the min and mag filters
+    pub fn with_filter(self, filter: Filter) -> TextureBuilder {
+        self.with_min_filter(filter).with_mag_filter(filter)
+    }
+
+    pub fn with_min_filter(mut self, filter: Filter) -> TextureBuilder {
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn with_mag_filter(mut self, filter: Filter) -> TextureBuilder {
+        self.mag_filter = filter;
+        self
+    }
+
+    // Generate mipmaps, blending between levels with the given filter. `None` turns mipmaps off.
+    pub fn with_mipmaps(mut self, filter: Option<Filter>) -> TextureBuilder {
+        self.mipmap_filter = filter;
+        self
+    }
+
+    // The smallest mip level to use (0 being the full size image)
+    pub fn with_max_mip_level(mut self, level: u32) -> TextureBuilder {
+        self.max_mip_level = level;
+        self
+    }
+
+    // Set the wrap mode in both directions
+    pub fn with_wrap(self, wrap: Wrap) -> TextureBuilder {
+        self.with_wrap_s(wrap).with_wrap_t(wrap)
+    }
+
+    pub fn with_wrap_s(mut self, wrap: Wrap) -> TextureBuilder {
+        self.wrap_s = wrap;
+        self
+    }
+
+    pub fn with_wrap_t(mut self, wrap: Wrap) -> TextureBuilder {
+        self.wrap_t = wrap;
+        self
+    }
+
+    // Anisotropic filtering, which keeps surfaces seen at an angle sharp. Clamped to what the driver supports (if any).
+    pub fn with_anisotropy(mut self, anisotropy: f32) -> TextureBuilder {
+        self.anisotropy = anisotropy.max(1.0);
+        self
+    }
+
+    pub fn with_lod_bias(mut self, lod_bias: f32) -> TextureBuilder {
+        self.lod_bias = lod_bias;
+        self
+    }
+
+    pub fn with_colour_space(mut self, colour_space: ColourSpace) -> TextureBuilder {
+        self.colour_space = colour_space;
+        self
+    }
+
+    // -- Getters -- //
+    pub fn min_filter(&self) -> Filter {
+        self.min_filter
+    }
+
+    pub fn has_mipmaps(&self) -> bool {
+        self.mipmap_filter.is_some()
+    }
+
+    pub fn max_mip_level(&self) -> u32 {
+        self.max_mip_level
+    }
+
+    pub fn colour_space(&self) -> ColourSpace {
+        self.colour_space
+    }
+
+    // -- Building -- //
+    // Create a texture from a decoded image. `name` is only used for error reporting.
+    pub fn build(&self, image: &DecodedImage, name: &str) -> Result<Texture, Error> {
+        let texture = Texture::from_image_with_colour_space(image, name, self.colour_space)?;
+        self.configure(&texture);
+        Ok(texture)
+    }
+
+    pub fn build_from_resource(&self, res: &Resources, name: &str) -> Result<Texture, Error> {
+        let image = res.load_image(name)
+            .map_err(|e| Error::Resource { name: String::from(name), inner: e })?;
+        self.build(&image, name)
+    }
+
+    /**
+    Apply the sampler settings to an existing texture, generating its mipmaps from its full size image if needed.
+    The colour space can't be changed after a texture is created, so is ignored.
+    (Note: unbinds the active texture unit's texture)
+    */
+    pub fn configure(&self, texture: &Texture) {
+        self.apply(gl::TEXTURE_2D, texture.id(), true);
+    }
+
+    /**
+    Apply the sampler settings to a texture, without generating mipmaps or changing how many mip levels it has
+    (eg/ for textures that were uploaded with their own mip levels). (Note: unbinds the active texture unit's texture)
+    */
+    pub fn set_parameters(&self, texture: &Texture) {
+        self.apply(gl::TEXTURE_2D, texture.id(), false);
+    }
+
+    // Apply the sampler settings to any kind of texture (eg/ `TEXTURE_2D_ARRAY`), optionally generating its mipmaps
+    pub(super) fn apply(&self, target: gl::types::GLenum, id: gl::types::GLuint, generate_mipmaps: bool) {
+        let min_filter = match (self.min_filter, self.mipmap_filter) {
+            (filter, None) => filter.gl_enum(),
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        };
+
+        unsafe {
+            gl::BindTexture(target, id);
+            gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as gl::types::GLint);
+            gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, self.mag_filter.gl_enum() as gl::types::GLint);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_S, self.wrap_s.gl_enum() as gl::types::GLint);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_T, self.wrap_t.gl_enum() as gl::types::GLint);
+            gl::TexParameterf(target, gl::TEXTURE_LOD_BIAS, self.lod_bias);
+
+            let max_anisotropy = max_anisotropy();
+            if max_anisotropy > 1.0 {
+                gl::TexParameterf(target, TEXTURE_MAX_ANISOTROPY, self.anisotropy.min(max_anisotropy));
+            }
+
+            if generate_mipmaps && self.has_mipmaps() {
+                gl::TexParameteri(target, gl::TEXTURE_MAX_LEVEL, self.max_mip_level.min(i32::MAX as u32) as gl::types::GLint);
+                gl::GenerateMipmap(target);
+            }
+
+            gl::BindTexture(target, 0);
+        }
+    }
+}
+
+impl Default for TextureBuilder {
+    fn default() -> Self {
+        TextureBuilder::new()
+    }
+}
+
+thread_local! {
+    // The driver's limit, once it's been asked. The GL context is only used from the thread that made it.
+    static MAX_ANISOTROPY: Cell<Option<f32>> = const { Cell::new(None) };
+}
+
+// The most anisotropic filtering the driver supports, or 1 if it doesn't support any. Only asks the driver once.
+fn max_anisotropy() -> f32 {
+    MAX_ANISOTROPY.with(|max| {
+        if max.get().is_none() {
+            max.set(Some(query_max_anisotropy()));
+        }
+        max.get().unwrap_or(1.0)
+    })
+}
+
+fn query_max_anisotropy() -> f32 {
+    let mut extension_count: gl::types::GLint = 0;
+    unsafe { gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count); }
+
+    let supported = (0..extension_count.max(0) as gl::types::GLuint).any(|index| unsafe {
+        let extension = gl::GetStringi(gl::EXTENSIONS, index);
+        !extension.is_null() && ANISOTROPY_EXTENSIONS.iter()
+            .any(|&name| CStr::from_ptr(extension as *const std::os::raw::c_char).to_bytes() == name.as_bytes())
+    });
+    if !supported {
+        return 1.0;
+    }
+
+    let mut max: gl::types::GLfloat = 1.0;
+    unsafe { gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max); }
+    max
+}
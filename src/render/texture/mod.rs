@@ -0,0 +1,296 @@
+extern crate gl;
+
+pub mod atlas;
+pub mod array;
+pub mod animation;
+pub mod builder;
+
+pub use self::atlas::{Atlas, AtlasBuilder, UvRect};
+pub use self::array::TextureArray;
+pub use self::animation::Animation;
+pub use self::builder::TextureBuilder;
+
+use crate::resources::Resources;
+use crate::resources::decode::{DecodedImage, PixelFormat};
+use crate::render::Error;
+use std::convert::TryInto;
+use serde::Deserialize;
+
+// How texels are sampled when a texture is scaled
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+impl Filter {
+    fn gl_enum(self) -> gl::types::GLenum {
+        match self {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+// What happens to texture coordinates outside of 0 -> 1
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl Wrap {
+    fn gl_enum(self) -> gl::types::GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/**
+How the colour channels of an image are interpreted. Colour textures (eg/ block faces) are usually authored in sRGB,
+data textures (eg/ normal maps) are linear. sRGB textures are converted to linear when sampled.
+*/
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColourSpace {
+    #[default]
+    Linear,
+    Srgb,
+}
+
+// How to upload pixels of a particular format
+struct Upload {
+    internal_format: gl::types::GLenum,
+    format: gl::types::GLenum,
+    data_type: gl::types::GLenum,
+    swizzle: [gl::types::GLint; 4],     // Where the red, green, blue and alpha the shader reads come from
+}
+
+impl Upload {
+    // sRGB is only applied to RGB(A) 8 bit formats, as OpenGL has no others
+    fn of(format: PixelFormat, colour_space: ColourSpace) -> Upload {
+        let srgb = colour_space == ColourSpace::Srgb;
+        let (internal_format, format, data_type) = match format {
+            PixelFormat::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+            PixelFormat::RG8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+            PixelFormat::RGB8 => (if srgb { gl::SRGB8 } else { gl::RGB8 }, gl::RGB, gl::UNSIGNED_BYTE),
+            PixelFormat::RGBA8 => (if srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 }, gl::RGBA, gl::UNSIGNED_BYTE),
+            PixelFormat::BGR8 => (if srgb { gl::SRGB8 } else { gl::RGB8 }, gl::BGR, gl::UNSIGNED_BYTE),
+            PixelFormat::BGRA8 => (if srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 }, gl::BGRA, gl::UNSIGNED_BYTE),
+            // 16 bit channels are stored native-endian, which is what OpenGL expects
+            PixelFormat::R16 => (gl::R16, gl::RED, gl::UNSIGNED_SHORT),
+            PixelFormat::RG16 => (gl::RG16, gl::RG, gl::UNSIGNED_SHORT),
+            PixelFormat::RGB16 => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT),
+            PixelFormat::RGBA16 => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT),
+        };
+
+        let (red, green, blue, alpha) = (gl::RED as gl::types::GLint, gl::GREEN as gl::types::GLint,
+            gl::BLUE as gl::types::GLint, gl::ALPHA as gl::types::GLint);
+        let one = gl::ONE as gl::types::GLint;
+        let swizzle = match format {
+            gl::RED => [red, red, red, one],        // Grey
+            gl::RG => [red, red, red, green],       // Grey and alpha
+            _ => [red, green, blue, alpha],
+        };
+
+        Upload { internal_format, format, data_type, swizzle }
+    }
+}
+
+pub struct Texture {
+    id: gl::types::GLuint,
+}
+
+impl Texture {
+    // Load a texture from a resource
+    pub fn from_resource(res: &Resources, name: &str) -> Result<Texture, Error> {
+        let image = res.load_image(name)
+            .map_err(|e| Error::Resource { name: String::from(name), inner: e })?;
+
+        Texture::from_image(&image, name)
+    }
+
+    // Load a texture from a resource, choosing how its colours are interpreted
+    pub fn from_resource_with_colour_space(res: &Resources, name: &str, colour_space: ColourSpace) -> Result<Texture, Error> {
+        let image = res.load_image(name)
+            .map_err(|e| Error::Resource { name: String::from(name), inner: e })?;
+
+        Texture::from_image_with_colour_space(&image, name, colour_space)
+    }
+
+    // Create a texture from a decoded image. `name` is only used for error reporting.
+    pub fn from_image(image: &DecodedImage, name: &str) -> Result<Texture, Error> {
+        Texture::from_image_with_colour_space(image, name, ColourSpace::Linear)
+    }
+
+    /**
+    Create a texture from a decoded image, choosing how its colours are interpreted. `name` is only used for error reporting.
+    Every `PixelFormat` is uploaded as-is, except that there are no sRGB grey or 16 bit formats, so those images
+    are converted to 8 bit RGBA when `ColourSpace::Srgb` is asked for.
+    */
+    pub fn from_image_with_colour_space(image: &DecodedImage, name: &str, colour_space: ColourSpace) -> Result<Texture, Error> {
+        Texture::from_levels(std::slice::from_ref(image), name, colour_space)
+    }
+
+    /**
+    Create a texture from a decoded image and its mip levels (each half the size of the last), which must all have
+    the same format. Only the levels given are used.
+    */
+    fn from_levels(levels: &[DecodedImage], name: &str, colour_space: ColourSpace) -> Result<Texture, Error> {
+        // Load the texture into OpenGL
+        let mut id : gl::types::GLuint = 0;
+        unsafe { 
+            gl::GenTextures(1, &mut id); 
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            // Configure this texture
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, levels.len().saturating_sub(1) as gl::types::GLint);
+
+            // Rows are tightly packed, so may not be a multiple of the default 4 bytes long
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        }
+        // Deletes the texture if an upload fails
+        let texture = Texture{id};
+
+        for (level, image) in levels.iter().enumerate() {
+            let converted;
+            let image = match (colour_space, image.format) {
+                (ColourSpace::Srgb, PixelFormat::RGB8) | (ColourSpace::Srgb, PixelFormat::RGBA8)
+                    | (ColourSpace::Srgb, PixelFormat::BGR8) | (ColourSpace::Srgb, PixelFormat::BGRA8) => image,
+                (ColourSpace::Srgb, _) => {
+                    converted = image.to_rgba8();
+                    &converted
+                },
+                (ColourSpace::Linear, _) => image,
+            };
+            let upload = Upload::of(image.format, colour_space);
+
+            let (width, height) = match (image.width.try_into(), image.height.try_into()) {
+                (Ok(width), Ok(height)) => (width, height),
+                _ => {
+                    unsafe {
+                        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                        gl::BindTexture(gl::TEXTURE_2D, 0);
+                    }
+                    return Err(Error::ImageTooLarge { name: String::from(name), size: (image.width, image.height) });
+                },
+            };
+
+            unsafe {
+                // Make grey images read as grey in every channel, rather than red
+                gl::TexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_RGBA, upload.swizzle.as_ptr());
+
+                // Push image data
+                gl::TexImage2D(
+                    gl::TEXTURE_2D, 
+                    level as gl::types::GLint, 
+                    upload.internal_format as gl::types::GLint, 
+                    width,
+                    height, 
+                    0, 
+                    upload.format, 
+                    upload.data_type,
+                    image.data.as_ptr() as *const std::ffi::c_void
+                );
+            }
+        }
+
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            // Don't leave the texture bound: textures are bound by whatever draws with them (see `bind()`)
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(texture)
+    }
+
+    /**
+    Replace the full size image (eg/ with the next frame of an animation), which must be the same size as the current one.
+    Mipmaps aren't updated, `TextureBuilder::configure` regenerates them. `name` is only used for error reporting.
+    (Note: unbinds the active texture unit's texture)
+    */
+    pub fn update_image(&self, image: &DecodedImage, name: &str) -> Result<(), Error> {
+        let (mut width, mut height): (gl::types::GLint, gl::types::GLint) = (0, 0);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_WIDTH, &mut width);
+            gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_HEIGHT, &mut height);
+        }
+        let size = (width.max(0) as u32, height.max(0) as u32);
+        if (image.width, image.height) != size {
+            unsafe { gl::BindTexture(gl::TEXTURE_2D, 0); }
+            return Err(Error::LayerSize { name: String::from(name), expected: size, found: (image.width, image.height) });
+        }
+
+        // The driver converts to the texture's format, so the data only has to be in one it can read
+        let image = image.to_rgba8();
+        unsafe {
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0, 0,   // Offset
+                width, height,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                image.data.as_ptr() as *const std::ffi::c_void
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(())
+    }
+
+    // Set the min and mag filters (Note: unbinds the active texture unit's texture)
+    pub fn set_filter(&self, filter: Filter) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter.gl_enum() as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter.gl_enum() as gl::types::GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    // Set the wrap mode in both directions (Note: unbinds the active texture unit's texture)
+    pub fn set_wrap(&self, wrap: Wrap) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap.gl_enum() as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap.gl_enum() as gl::types::GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    // Bind the texture to a texture unit, for a `sampler2D` uniform set to that unit to read
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+        }
+    }
+
+    // Getter for own ID
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+}
+
+impl Drop for Texture {
+    /**
+    When the `Texture` object is dropped, we need to delete the texture from the OpenGL context
+    */
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,111 @@
+/*
+Values that can be set as uniforms. See `Program::set_uniform`.
+*/
+
+extern crate nalgebra;
+
+use nalgebra::{Vector2, Vector3, Vector4, Matrix3, Matrix4};
+use serde::Deserialize;
+use crate::obj::vertex::Coords3D;
+
+// A value that can be uploaded to a uniform of the matching GLSL type
+pub trait Uniform {
+    /**
+    Set the uniform at `location` in the current program.
+    Note: `location` may be -1 (for a uniform that doesn't exist), which GL ignores.
+    */
+    fn set_at(&self, location: gl::types::GLint);
+}
+
+// The texture unit a `sampler2D` (etc) uniform reads from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sampler(pub u32);
+
+/**
+A uniform value that isn't known until runtime (eg/ read from a data file).
+In data files, whole numbers are ints, numbers with a decimal point are floats, and arrays of 2 to 4 floats are vectors.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl Uniform for UniformValue {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe {
+            match *self {
+                UniformValue::Int(value) => gl::Uniform1i(location, value),
+                UniformValue::Float(value) => gl::Uniform1f(location, value),
+                UniformValue::Vec2([x, y]) => gl::Uniform2f(location, x, y),
+                UniformValue::Vec3([x, y, z]) => gl::Uniform3f(location, x, y, z),
+                UniformValue::Vec4([x, y, z, w]) => gl::Uniform4f(location, x, y, z, w),
+            }
+        }
+    }
+}
+
+impl<U: Uniform> Uniform for &U {
+    fn set_at(&self, location: gl::types::GLint) {
+        (*self).set_at(location)
+    }
+}
+
+impl Uniform for f32 {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::Uniform1f(location, *self); }
+    }
+}
+
+impl Uniform for i32 {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::Uniform1i(location, *self); }
+    }
+}
+
+impl Uniform for Sampler {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::Uniform1i(location, self.0 as gl::types::GLint); }
+    }
+}
+
+impl Uniform for Coords3D {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::Uniform3f(location, self.x, self.y, self.z); }
+    }
+}
+
+impl Uniform for Vector2<f32> {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::Uniform2f(location, self.x, self.y); }
+    }
+}
+
+impl Uniform for Vector3<f32> {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::Uniform3f(location, self.x, self.y, self.z); }
+    }
+}
+
+impl Uniform for Vector4<f32> {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::Uniform4f(location, self.x, self.y, self.z, self.w); }
+    }
+}
+
+// nalgebra matrices are stored column major, the same as GLSL, so don't need transposing
+impl Uniform for Matrix3<f32> {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::UniformMatrix3fv(location, 1, gl::FALSE, self.as_ptr()); }
+    }
+}
+
+impl Uniform for Matrix4<f32> {
+    fn set_at(&self, location: gl::types::GLint) {
+        unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, self.as_ptr()); }
+    }
+}
@@ -0,0 +1,89 @@
+/*
+Uniform buffer objects: a block of uniforms that's uploaded once, and read by every program that declares it.
+
+Each kind of block gets a fixed binding point. Programs have their blocks attached to those binding points
+when they're linked (see `BLOCK_BINDINGS`), so a buffer only has to be bound to its binding point once.
+*/
+
+use std::marker::PhantomData;
+
+use crate::render::frame::FrameData;
+
+// Uniform block name -> binding point, for every block the game uses
+pub const BLOCK_BINDINGS: [(&str, gl::types::GLuint); 1] = [
+    (FrameData::BLOCK_NAME, FrameData::BINDING),
+];
+
+/**
+A uniform buffer holding a single `T`.
+`T` must be `#[repr(C)]` and laid out the same as the GLSL block with `layout (std140)`: mostly this means
+vec3s are padded to vec4s, and the whole struct is padded to a multiple of 16 bytes.
+*/
+pub struct UniformBuffer<T: Copy> {
+    id: gl::types::GLuint,
+    binding: gl::types::GLuint,
+    _contents: PhantomData<T>,
+}
+
+impl<T: Copy> UniformBuffer<T> {
+    // Create a buffer holding `data`, and bind it to a binding point
+    pub fn new(binding: gl::types::GLuint, data: &T) -> UniformBuffer<T> {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, id);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                std::mem::size_of::<T>() as gl::types::GLsizeiptr,
+                data as *const T as *const gl::types::GLvoid,
+                gl::DYNAMIC_DRAW,   // usage hint: Data changes every frame, used for drawing
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, id);
+        }
+
+        UniformBuffer {
+            id,
+            binding,
+            _contents: PhantomData,
+        }
+    }
+
+    // Replace the buffer's contents
+    pub fn update(&self, data: &T) {
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.id);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                std::mem::size_of::<T>() as gl::types::GLsizeiptr,
+                data as *const T as *const gl::types::GLvoid,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    // Bind the buffer to its binding point again (eg/ if something else has been bound there)
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, self.binding, self.id);
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn binding(&self) -> gl::types::GLuint {
+        self.binding
+    }
+}
+
+impl<T: Copy> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+/*
+Shader variants: one set of shader sources compiled several ways, by turning features on with `#define`s.
+eg/ a terrain program with `ALPHA_CUTOUT` for leaves, and without it for solid blocks:
+```glsl
+#ifdef ALPHA_CUTOUT
+    if (colour.a < 0.5) discard;
+#endif
+```
+Each variant is compiled the first time it's asked for, then kept for as long as the `ProgramVariants` is.
+Materials get theirs through `Assets::program_variants`, so every material using a program shares one set of variants.
+*/
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::assets::Handle;
+use crate::resources::Resources;
+use crate::render::binary_cache::BinaryCache;
+use crate::render::program::Program;
+use crate::render::Error;
+
+pub struct ProgramVariants {
+    shader_names: Vec<String>,
+    binary_cache: Option<BinaryCache>,
+    variants: RefCell<HashMap<Vec<String>, Handle<Program>>>,   // Define set -> program
+}
+
+impl ProgramVariants {
+    pub fn new<S: AsRef<str>>(shader_names: &[S]) -> ProgramVariants {
+        ProgramVariants {
+            shader_names: shader_names.iter().map(|name| String::from(name.as_ref())).collect(),
+            binary_cache: None,
+            variants: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Load variants from (and store them in) a binary cache
+    pub fn set_binary_cache(&mut self, binary_cache: Option<BinaryCache>) {
+        self.binary_cache = binary_cache;
+    }
+
+    /**
+    Get the variant with the given defines (in any order) turned on, compiling it if needed.
+    Each define is set to `1`, so can be checked with `#ifdef` or `#if`.
+    */
+    pub fn get<S: AsRef<str>>(&self, res: &Resources, defines: &[S]) -> Result<Handle<Program>, Error> {
+        let define_set = define_set(defines);
+        if let Some(program) = self.variants.borrow().get(&define_set) {
+            return Ok(Handle::clone(program));
+        }
+
+        let program = Handle::new(build_variant(res, &self.shader_names, &define_set, self.binary_cache.as_ref())?);
+        self.variants.borrow_mut().insert(define_set, Handle::clone(&program));
+
+        Ok(program)
+    }
+
+    // Add a variant that's already been built (eg/ by the `render::loader::Loader`)
+    pub fn insert<S: AsRef<str>>(&self, defines: &[S], program: Program) -> Handle<Program> {
+        let program = Handle::new(program);
+        self.variants.borrow_mut().insert(define_set(defines), Handle::clone(&program));
+
+        program
+    }
+
+    /**
+    Rebuild every compiled variant (eg/ after the sources change). If any fails, the current variants are all kept
+    and the error is returned. Handles to the old programs stay valid, but aren't updated: `get` the new ones.
+    */
+    pub fn reload(&self, res: &Resources) -> Result<(), Error> {
+        let define_sets: Vec<Vec<String>> = self.variants.borrow().keys().cloned().collect();
+
+        let mut reloaded = HashMap::new();
+        for define_set in define_sets {
+            let program = build_variant(res, &self.shader_names, &define_set, self.binary_cache.as_ref())?;
+            reloaded.insert(define_set, Handle::new(program));
+        }
+        *self.variants.borrow_mut() = reloaded;
+
+        Ok(())
+    }
+
+    // Whether any compiled variant was built from the given resource
+    pub fn depends_on(&self, resource_name: &str) -> bool {
+        self.variants.borrow().values()
+            .any(|program| program.files().iter().any(|name| name == resource_name))
+    }
+
+    // Every resource the compiled variants were built from (includes are resolved whatever the defines, so they all share these)
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut files: Vec<String> = self.variants.borrow().values()
+            .flat_map(|program| program.files().iter().cloned())
+            .collect();
+        files.sort();
+        files.dedup();
+
+        files
+    }
+
+    pub fn shader_names(&self) -> &[String] {
+        &self.shader_names
+    }
+}
+
+// Compile a program from shader resources, with the given defines turned on
+pub fn build_variant<S: AsRef<str>>(
+    res: &Resources,
+    shader_names: &[S],
+    defines: &[String],
+    binary_cache: Option<&BinaryCache>
+) -> Result<Program, Error> {
+    let defines: Vec<(&str, &str)> = defines.iter().map(|name| (name.as_str(), "1")).collect();
+
+    Program::from_resources(res, shader_names, &defines, binary_cache)
+}
+
+// A set of defines, in a consistent order, so it can be used as a key
+pub fn define_set<S: AsRef<str>>(defines: &[S]) -> Vec<String> {
+    let mut define_set: Vec<String> = defines.iter().map(|name| String::from(name.as_ref())).collect();
+    define_set.sort();
+    define_set.dedup();
+
+    define_set
+}
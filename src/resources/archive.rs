@@ -0,0 +1,76 @@
+/*
+Zip archives as a resource source, so a whole resource pack can be shipped as a single file.
+Resource names are looked up from the root of the archive, so the archive should contain
+`shaders/...`, `textures/...` etc at its top level.
+*/
+
+extern crate zip;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use zip::ZipArchive;
+
+use super::Error;
+use super::source::Source;
+use super::path::ResourcePath;
+
+pub struct ZipSource {
+    // Reading from the archive needs mutable access, but loading resources doesn't (and may happen on any thread)
+    archive: Mutex<ZipArchive<fs::File>>,
+    // Lookup key of every file in the archive -> its name in the archive, so we can check for a resource without seeking
+    file_names: HashMap<String, String>,
+}
+
+impl ZipSource {
+    pub fn open(archive_path: &Path) -> Result<ZipSource, Error> {
+        let archive = ZipArchive::new(fs::File::open(archive_path)?)?;
+
+        // Entries with names that aren't valid resource paths (eg/ `../evil.png`) can never be loaded
+        let mut file_names = HashMap::new();
+        for name in archive.file_names().filter(|name| !name.ends_with('/')) {  // Skip directory entries
+            if let Ok(path) = ResourcePath::new(name) {
+                file_names.entry(String::from(path.key())).or_insert_with(|| String::from(name));
+            }
+        }
+
+        Ok(ZipSource {
+            archive: Mutex::new(archive),
+            file_names,
+        })
+    }
+}
+
+impl Source for ZipSource {
+    fn contains(&self, path: &ResourcePath) -> bool {
+        self.file_names.contains_key(path.key())
+    }
+
+    fn read(&self, path: &ResourcePath) -> Result<Vec<u8>, Error> {
+        let file_name = self.file_names.get(path.key())
+            .ok_or_else(|| Error::NotFound(String::from(path.as_str())))?;
+
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = archive.by_name(file_name)?;
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    fn list(&self) -> Vec<ResourcePath> {
+        self.file_names.values()
+            .filter_map(|name| ResourcePath::new(name).ok())
+            .collect()
+    }
+}
+
+// Returns whether a path looks like an archive we can mount
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
@@ -0,0 +1,366 @@
+/*
+Image decoding: Works out the format of an image from its contents (or its name), and decodes it into
+a common `DecodedImage` that the renderer can upload without caring where it came from.
+
+PNG, JPEG, TGA and BMP are decoded through the `image` crate. DDS and KTX are parsed here, and only
+uncompressed images are supported (the first mip level of the first face/layer is read).
+*/
+
+use std::convert::TryInto;
+use std::io::Cursor;
+use image::{self, ImageDecoder, ColorType};
+use super::Error;
+
+// Layout of the pixels in a `DecodedImage`. 16 bit channels are stored native-endian.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PixelFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    BGR8,
+    BGRA8,
+    R16,
+    RG16,
+    RGB16,
+    RGBA16,
+}
+
+impl PixelFormat {
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::R8 | PixelFormat::R16 => 1,
+            PixelFormat::RG8 | PixelFormat::RG16 => 2,
+            PixelFormat::RGB8 | PixelFormat::BGR8 | PixelFormat::RGB16 => 3,
+            PixelFormat::RGBA8 | PixelFormat::BGRA8 | PixelFormat::RGBA16 => 4,
+        }
+    }
+
+    pub fn bytes_per_channel(self) -> usize {
+        match self {
+            PixelFormat::R16 | PixelFormat::RG16 | PixelFormat::RGB16 | PixelFormat::RGBA16 => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        self.channels() * self.bytes_per_channel()
+    }
+
+    fn from_color_type(color_type: ColorType) -> Option<PixelFormat> {
+        match color_type {
+            ColorType::Gray(8) => Some(PixelFormat::R8),
+            ColorType::GrayA(8) => Some(PixelFormat::RG8),
+            ColorType::RGB(8) => Some(PixelFormat::RGB8),
+            ColorType::RGBA(8) => Some(PixelFormat::RGBA8),
+            ColorType::BGR(8) => Some(PixelFormat::BGR8),
+            ColorType::BGRA(8) => Some(PixelFormat::BGRA8),
+            ColorType::Gray(16) => Some(PixelFormat::R16),
+            ColorType::GrayA(16) => Some(PixelFormat::RG16),
+            ColorType::RGB(16) => Some(PixelFormat::RGB16),
+            ColorType::RGBA(16) => Some(PixelFormat::RGBA16),
+            _ => None,
+        }
+    }
+}
+
+// Container formats we know how to read
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Tga,
+    Bmp,
+    Dds,
+    Ktx,
+}
+
+impl ImageFormat {
+    /**
+    Work out the format of an image: from its magic bytes if it has any, otherwise from its extension
+    (TGA files don't have a magic number)
+    */
+    pub fn detect(name: &str, data: &[u8]) -> Option<ImageFormat> {
+        const KTX_MAGIC: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+        const MAGIC: [(&[u8], ImageFormat); 5] = [
+            (b"\x89PNG", ImageFormat::Png),
+            (&[0xFF, 0xD8, 0xFF], ImageFormat::Jpeg),
+            (b"BM", ImageFormat::Bmp),
+            (b"DDS ", ImageFormat::Dds),
+            (&KTX_MAGIC, ImageFormat::Ktx),
+        ];
+
+        if let Some(&(_, format)) = MAGIC.iter().find(|&&(magic, _)| data.starts_with(magic)) {
+            return Some(format);
+        }
+
+        const EXTENSIONS: [(&str, ImageFormat); 7] = [
+            (".png", ImageFormat::Png),
+            (".jpg", ImageFormat::Jpeg),
+            (".jpeg", ImageFormat::Jpeg),
+            (".tga", ImageFormat::Tga),
+            (".bmp", ImageFormat::Bmp),
+            (".dds", ImageFormat::Dds),
+            (".ktx", ImageFormat::Ktx),
+        ];
+        let lower_name = name.to_ascii_lowercase();
+        EXTENSIONS.iter()
+            .find(|&&(extension, _)| lower_name.ends_with(extension))
+            .map(|&(_, format)| format)
+    }
+}
+
+/**
+A decoded image. Rows are stored top to bottom, tightly packed.
+*/
+#[derive(Clone)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub data: Vec<u8>,
+}
+
+impl DecodedImage {
+    // Decode an image, detecting its format from the data or name
+    pub fn decode(name: &str, data: Vec<u8>) -> Result<DecodedImage, Error> {
+        let format = ImageFormat::detect(name, &data).ok_or(Error::UnknownImageFormat)?;
+        DecodedImage::decode_as(format, data)
+    }
+
+    pub fn decode_as(format: ImageFormat, data: Vec<u8>) -> Result<DecodedImage, Error> {
+        match format {
+            ImageFormat::Png => from_decoder(image::png::PNGDecoder::new(Cursor::new(data))?),
+            ImageFormat::Jpeg => from_decoder(image::jpeg::JPEGDecoder::new(Cursor::new(data))?),
+            ImageFormat::Tga => from_decoder(image::tga::TGADecoder::new(Cursor::new(data))?),
+            ImageFormat::Bmp => from_decoder(image::bmp::BMPDecoder::new(Cursor::new(data))?),
+            ImageFormat::Dds => decode_dds(&data),
+            ImageFormat::Ktx => decode_ktx(&data),
+        }
+    }
+
+    /**
+    Convert the image to 8 bit RGBA. Grey is copied to each colour channel, missing alpha is opaque,
+    and 16 bit channels are rounded down to 8 bits.
+    */
+    pub fn to_rgba8(&self) -> DecodedImage {
+        let bytes_per_channel = self.format.bytes_per_channel();
+        // The most significant byte of each channel
+        let channel = |pixel: &[u8], index: usize| -> u8 {
+            let start = index * bytes_per_channel;
+            if bytes_per_channel == 2 {
+                (u16::from_ne_bytes([pixel[start], pixel[start + 1]]) >> 8) as u8
+            } else {
+                pixel[start]
+            }
+        };
+
+        let mut data = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        for pixel in self.data.chunks_exact(self.format.bytes_per_pixel()) {
+            let rgba = match self.format {
+                PixelFormat::R8 | PixelFormat::R16 => {
+                    let grey = channel(pixel, 0);
+                    [grey, grey, grey, 255]
+                },
+                PixelFormat::RG8 | PixelFormat::RG16 => {
+                    let grey = channel(pixel, 0);
+                    [grey, grey, grey, channel(pixel, 1)]
+                },
+                PixelFormat::RGB8 | PixelFormat::RGB16 => [channel(pixel, 0), channel(pixel, 1), channel(pixel, 2), 255],
+                PixelFormat::RGBA8 | PixelFormat::RGBA16 => [channel(pixel, 0), channel(pixel, 1), channel(pixel, 2), channel(pixel, 3)],
+                PixelFormat::BGR8 => [pixel[2], pixel[1], pixel[0], 255],
+                PixelFormat::BGRA8 => [pixel[2], pixel[1], pixel[0], pixel[3]],
+            };
+            data.extend_from_slice(&rgba);
+        }
+
+        DecodedImage {
+            width: self.width,
+            height: self.height,
+            format: PixelFormat::RGBA8,
+            data,
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(other: image::ImageError) -> Self {
+        Error::ImageDecodeFailed(other)
+    }
+}
+
+fn from_decoder<'a, D: ImageDecoder<'a>>(decoder: D) -> Result<DecodedImage, Error> {
+    let (width, height) = decoder.dimensions();
+    let color_type = decoder.colortype();
+    let format = PixelFormat::from_color_type(color_type)
+        .ok_or_else(|| Error::InvalidImage(format!("Unsupported colour type {:?}", color_type)))?;
+
+    let mut data = decoder.read_image()?;
+    if format.bytes_per_channel() == 2 {
+        // The `image` crate gives 16 bit channels big-endian
+        for channel in data.chunks_exact_mut(2) {
+            let value = u16::from_be_bytes([channel[0], channel[1]]);
+            channel.copy_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    Ok(DecodedImage {
+        width: width.try_into().map_err(|_| Error::InvalidImage(String::from("Image is too wide")))?,
+        height: height.try_into().map_err(|_| Error::InvalidImage(String::from("Image is too tall")))?,
+        format,
+        data,
+    })
+}
+
+// Read a little-endian u32 at the given offset
+fn read_u32_le(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| Error::InvalidImage(String::from("Unexpected end of file")))
+}
+
+// Take the pixel data for a `width` x `height` image from the start of `data`, dropping any row padding
+fn take_rows(data: &[u8], width: u32, height: u32, format: PixelFormat, row_alignment: usize) -> Result<Vec<u8>, Error> {
+    // Sizes come from the file's header, so may be nonsense: check they fit before allocating anything
+    let too_large = || Error::InvalidImage(format!("Image size {}x{} is too large", width, height));
+    let row_len = (width as usize).checked_mul(format.bytes_per_pixel()).ok_or_else(too_large)?;
+    let row_stride = row_len.div_ceil(row_alignment).checked_mul(row_alignment).ok_or_else(too_large)?;
+    let total = row_stride.checked_mul(height as usize).ok_or_else(too_large)?;
+    let pixels_len = row_len.checked_mul(height as usize).ok_or_else(too_large)?;
+    if data.len() < total {
+        return Err(Error::InvalidImage(String::from("Not enough pixel data")));
+    }
+
+    let mut pixels = Vec::with_capacity(pixels_len);
+    for row in data[..total].chunks(row_stride) {
+        pixels.extend_from_slice(&row[..row_len]);
+    }
+
+    Ok(pixels)
+}
+
+// -- DDS -- //
+fn decode_dds(data: &[u8]) -> Result<DecodedImage, Error> {
+    // Header offsets, from the start of the file (including the 4 byte "DDS " magic)
+    const HEIGHT: usize = 12;
+    const WIDTH: usize = 16;
+    const PF_FLAGS: usize = 80;
+    const PF_BIT_COUNT: usize = 88;
+    const PF_R_MASK: usize = 92;
+    const PF_G_MASK: usize = 96;
+    const PF_B_MASK: usize = 100;
+    const PF_A_MASK: usize = 104;
+    const DATA_START: usize = 128;
+
+    // Pixel format flags
+    const DDPF_ALPHAPIXELS: u32 = 0x1;
+    const DDPF_FOURCC: u32 = 0x4;
+    const DDPF_RGB: u32 = 0x40;
+    const DDPF_LUMINANCE: u32 = 0x20000;
+
+    let height = read_u32_le(data, HEIGHT)?;
+    let width = read_u32_le(data, WIDTH)?;
+    let flags = read_u32_le(data, PF_FLAGS)?;
+    if flags & DDPF_FOURCC != 0 {
+        return Err(Error::InvalidImage(String::from("Compressed DDS images are not supported")));
+    }
+
+    let has_alpha = flags & DDPF_ALPHAPIXELS != 0;
+    let masks = (
+        read_u32_le(data, PF_BIT_COUNT)?,
+        read_u32_le(data, PF_R_MASK)?,
+        read_u32_le(data, PF_G_MASK)?,
+        read_u32_le(data, PF_B_MASK)?,
+        if has_alpha { read_u32_le(data, PF_A_MASK)? } else { 0 },
+    );
+
+    let format = if flags & DDPF_RGB != 0 {
+        match masks {
+            (32, 0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000) => PixelFormat::RGBA8,
+            (32, 0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000) => PixelFormat::BGRA8,
+            (24, 0xFF, 0xFF00, 0xFF_0000, 0) => PixelFormat::RGB8,
+            (24, 0xFF_0000, 0xFF00, 0xFF, 0) => PixelFormat::BGR8,
+            _ => return Err(Error::InvalidImage(format!("Unsupported DDS pixel layout {:?}", masks))),
+        }
+    } else if flags & DDPF_LUMINANCE != 0 {
+        match masks {
+            (8, 0xFF, _, _, 0) => PixelFormat::R8,
+            (16, 0xFF, _, _, 0xFF00) => PixelFormat::RG8,
+            (16, 0xFFFF, _, _, 0) => PixelFormat::R16,
+            _ => return Err(Error::InvalidImage(format!("Unsupported DDS luminance layout {:?}", masks))),
+        }
+    } else {
+        return Err(Error::InvalidImage(String::from("Unsupported DDS pixel format")));
+    };
+
+    // DDS is little-endian, so 16 bit channels need swapping on big-endian machines
+    let mut pixels = take_rows(&data[DATA_START.min(data.len())..], width, height, format, 1)?;
+    if format.bytes_per_channel() == 2 {
+        for channel in pixels.chunks_exact_mut(2) {
+            let value = u16::from_le_bytes([channel[0], channel[1]]);
+            channel.copy_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    Ok(DecodedImage { width, height, format, data: pixels })
+}
+
+// -- KTX -- //
+fn decode_ktx(data: &[u8]) -> Result<DecodedImage, Error> {
+    // GL enums used by KTX headers
+    const GL_UNSIGNED_BYTE: u32 = 0x1401;
+    const GL_UNSIGNED_SHORT: u32 = 0x1403;
+    const GL_RED: u32 = 0x1903;
+    const GL_RGB: u32 = 0x1907;
+    const GL_RGBA: u32 = 0x1908;
+    const GL_RG: u32 = 0x8227;
+    const GL_BGR: u32 = 0x80E0;
+    const GL_BGRA: u32 = 0x80E1;
+    const LITTLE_ENDIAN: u32 = 0x0403_0201;
+
+    // Header fields are u32s following the 12 byte identifier
+    let swap = read_u32_le(data, 12)? != LITTLE_ENDIAN;
+    let field = |index: usize| -> Result<u32, Error> {
+        let value = read_u32_le(data, 16 + index * 4)?;
+        Ok(if swap { value.swap_bytes() } else { value })
+    };
+
+    let gl_type = field(0)?;
+    let gl_format = field(2)?;
+    let width = field(5)?;
+    let height = field(6)?.max(1);  // 1D textures have a height of 0
+    let key_value_bytes = field(11)? as usize;
+
+    if gl_type == 0 {
+        return Err(Error::InvalidImage(String::from("Compressed KTX images are not supported")));
+    }
+
+    let format = match (gl_type, gl_format) {
+        (GL_UNSIGNED_BYTE, GL_RED) => PixelFormat::R8,
+        (GL_UNSIGNED_BYTE, GL_RG) => PixelFormat::RG8,
+        (GL_UNSIGNED_BYTE, GL_RGB) => PixelFormat::RGB8,
+        (GL_UNSIGNED_BYTE, GL_RGBA) => PixelFormat::RGBA8,
+        (GL_UNSIGNED_BYTE, GL_BGR) => PixelFormat::BGR8,
+        (GL_UNSIGNED_BYTE, GL_BGRA) => PixelFormat::BGRA8,
+        (GL_UNSIGNED_SHORT, GL_RED) => PixelFormat::R16,
+        (GL_UNSIGNED_SHORT, GL_RG) => PixelFormat::RG16,
+        (GL_UNSIGNED_SHORT, GL_RGB) => PixelFormat::RGB16,
+        (GL_UNSIGNED_SHORT, GL_RGBA) => PixelFormat::RGBA16,
+        _ => return Err(Error::InvalidImage(format!("Unsupported KTX type/format {:#x}/{:#x}", gl_type, gl_format))),
+    };
+
+    // The first mip level follows the key/value data, prefixed by its size. KTX rows are 4 byte aligned.
+    let image_start = (64 + 4usize).saturating_add(key_value_bytes);
+    let mut pixels = take_rows(&data[image_start.min(data.len())..], width, height, format, 4)?;
+
+    // 16 bit channels are in the file's endianness
+    if format.bytes_per_channel() == 2 {
+        for channel in pixels.chunks_exact_mut(2) {
+            let value = u16::from_le_bytes([channel[0], channel[1]]);
+            let value = if swap { value.swap_bytes() } else { value };
+            channel.copy_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    Ok(DecodedImage { width, height, format, data: pixels })
+}
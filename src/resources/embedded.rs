@@ -0,0 +1,48 @@
+/*
+A copy of `/assets` compiled into the binary by the build script (see `scripts/embed_assets.rs`),
+so the game can still run when the assets folder can't be found.
+*/
+
+use super::Error;
+use super::source::Source;
+use super::path::ResourcePath;
+
+include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
+pub struct EmbeddedSource;
+
+impl EmbeddedSource {
+    pub fn new() -> EmbeddedSource {
+        EmbeddedSource
+    }
+
+    fn find(&self, path: &ResourcePath) -> Option<&'static [u8]> {
+        EMBEDDED_ASSETS.iter()
+            .find(|&&(name, _)| name.to_lowercase() == path.key())
+            .map(|&(_, contents)| contents)
+    }
+}
+
+impl Default for EmbeddedSource {
+    fn default() -> Self {
+        EmbeddedSource::new()
+    }
+}
+
+impl Source for EmbeddedSource {
+    fn contains(&self, path: &ResourcePath) -> bool {
+        self.find(path).is_some()
+    }
+
+    fn read(&self, path: &ResourcePath) -> Result<Vec<u8>, Error> {
+        self.find(path)
+            .map(|contents| contents.to_vec())
+            .ok_or_else(|| Error::NotFound(String::from(path.as_str())))
+    }
+
+    fn list(&self) -> Vec<ResourcePath> {
+        EMBEDDED_ASSETS.iter()
+            .filter_map(|&(name, _)| ResourcePath::new(name).ok())
+            .collect()
+    }
+}
@@ -0,0 +1,342 @@
+/**
+This file handles the loading of assets and other resources
+*/
+
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::fmt;
+use std::io;
+use std::ffi;
+use std::time::SystemTime;
+
+pub mod source;
+pub mod archive;
+pub mod watcher;
+pub mod embedded;
+pub mod decode;
+pub mod path;
+
+use self::source::{Source, DirSource};
+use self::archive::ZipSource;
+use self::embedded::EmbeddedSource;
+use self::decode::DecodedImage;
+use self::path::ResourcePath;
+
+// Custom errors for this module
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    FailedToGetExePath,
+    FileContainsNullByte,
+    ImageDecodeFailed(image::ImageError),
+    UnknownImageFormat,
+    InvalidImage(String),
+    NotFound(String),
+    InvalidPath { name: String, reason: &'static str },
+    Archive(zip::result::ZipError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::FailedToGetExePath => write!(f, "Failed to get the executable's path"),
+            Error::FileContainsNullByte => write!(f, "File contains a null byte"),
+            Error::ImageDecodeFailed(e) => write!(f, "Failed to decode image: {}", e),
+            Error::UnknownImageFormat => write!(f, "Unknown image format"),
+            Error::InvalidImage(message) => write!(f, "Invalid image: {}", message),
+            Error::NotFound(name) => write!(f, "Resource not found: {}", name),
+            Error::InvalidPath { name, reason } => write!(f, "Invalid resource name {:?}: {}", name, reason),
+            Error::Archive(e) => write!(f, "Failed to read archive: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::ImageDecodeFailed(e) => Some(e),
+            Error::Archive(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Create a caster for io:Error to our Error
+impl From<io::Error> for Error {
+    fn from(other: io::Error) -> Self {
+        Error::Io(other)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(other: zip::result::ZipError) -> Self {
+        Error::Archive(other)
+    }
+}
+
+/**
+How the copy of the assets compiled into the binary should be used
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Embedded {
+    Never,      // Only use the assets on disk
+    Fallback,   // Use the embedded copy for anything missing from the assets on disk
+    Primary,    // Use the embedded copy over the assets on disk
+}
+
+/**
+A named source that resources can be loaded from (base assets, a resource pack, a mod, etc)
+*/
+struct Pack {
+    name: String,
+    source: Box<dyn Source>,
+}
+
+/**
+An ordered stack of packs. When loading a resource, the packs are searched from the highest priority
+(the most recently added) down to the lowest, and the first pack that contains the resource supplies it.
+Resource names are validated as `ResourcePath`s first: invalid names are never found, and fail to load.
+*/
+pub struct Resources {
+    packs: Vec<Pack>,   // Lowest priority first
+}
+
+impl Resources {
+    /**
+    Create a new Resources object for a given Path (either a folder or a `.zip` archive).
+    The embedded assets are used for anything that can't be found there.
+    */
+    pub fn from_relative_exe_path(rel_path: &Path) -> Result<Resources, Error> {
+        Resources::from_relative_exe_path_with_embedded(rel_path, Embedded::Fallback)
+    }
+
+    /**
+    Create a new Resources object for a given Path, choosing how to use the embedded assets.
+    If the path doesn't exist, only the embedded assets are used (unless they're disabled, when this errors).
+    */
+    pub fn from_relative_exe_path_with_embedded(rel_path: &Path, embedded: Embedded) -> Result<Resources, Error> {
+        let disk_path = exe_dir().ok()
+            .map(|exe_path| exe_path.join(rel_path))
+            .filter(|path| path.exists());
+
+        let disk_pack = match disk_path {
+            Some(path) => Some(Pack {
+                name: String::from("base"),
+                source: open_source(&path)?,
+            }),
+            None if embedded == Embedded::Never => {
+                return Err(Error::NotFound(rel_path.to_string_lossy().into_owned()));
+            },
+            None => None,
+        };
+
+        let mut packs = Vec::new();
+        if embedded == Embedded::Fallback {
+            packs.push(embedded_pack());
+        }
+        packs.extend(disk_pack);
+        if embedded == Embedded::Primary {
+            packs.push(embedded_pack());
+        }
+
+        Ok(Resources { packs })
+    }
+
+    /**
+    Create a new Resources object using only the assets embedded in the binary
+    */
+    pub fn from_embedded() -> Resources {
+        Resources {
+            packs: vec![embedded_pack()],
+        }
+    }
+
+    // -- Pack management -- //
+    /**
+    Add a pack (either a folder or a `.zip` archive) on top of the stack.
+    It takes priority over every pack already added.
+    */
+    pub fn add_pack(&mut self, name: &str, path: &Path) -> Result<(), Error> {
+        let source = open_source(path)?;
+        self.add_pack_source(name, source);
+        Ok(())
+    }
+
+    /**
+    Add a pack from any resource source on top of the stack.
+    */
+    pub fn add_pack_source(&mut self, name: &str, source: Box<dyn Source>) {
+        self.packs.push(Pack {
+            name: String::from(name),
+            source,
+        });
+    }
+
+    /**
+    Add a pack, with a path relative to the executable, on top of the stack.
+    */
+    pub fn add_pack_from_relative_exe_path(&mut self, name: &str, rel_path: &Path) -> Result<(), Error> {
+        let path = exe_dir()?.join(rel_path);
+        self.add_pack(name, &path)
+    }
+
+    /**
+    Add every sub-folder and `.zip` archive of a folder (relative to the executable) as a pack, named after the file.
+    Packs are added in alphabetical order, so later names take priority.
+    Does nothing if the folder doesn't exist.
+    */
+    pub fn add_packs_from_relative_exe_path(&mut self, rel_path: &Path) -> Result<(), Error> {
+        let packs_dir = exe_dir()?.join(rel_path);
+        if !packs_dir.is_dir() {
+            return Ok(());
+        }
+
+        let mut pack_dirs: Vec<PathBuf> = fs::read_dir(packs_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_dir() || archive::is_archive(path))
+            .collect();
+        pack_dirs.sort();
+
+        for pack_dir in pack_dirs {
+            let name = pack_dir.file_name().unwrap().to_string_lossy().into_owned();
+            self.add_pack(&name, &pack_dir)?;
+        }
+
+        Ok(())
+    }
+
+    /**
+    Remove all packs with the given name. Returns whether any were removed.
+    */
+    pub fn remove_pack(&mut self, name: &str) -> bool {
+        let count = self.packs.len();
+        self.packs.retain(|pack| pack.name != name);
+        count != self.packs.len()
+    }
+
+    // Names of the loaded packs, highest priority first
+    pub fn pack_names(&self) -> Vec<&str> {
+        self.packs.iter().rev().map(|pack| pack.name.as_str()).collect()
+    }
+
+    // Name of the pack a resource will be loaded from, if any pack contains it
+    pub fn provider_of(&self, resource_name: &str) -> Option<&str> {
+        let path = ResourcePath::new(resource_name).ok()?;
+        self.find_pack(&path).map(|pack| pack.name.as_str())
+    }
+
+    // Names of every pack containing a resource, highest priority first
+    pub fn providers_of(&self, resource_name: &str) -> Vec<&str> {
+        let path = match ResourcePath::new(resource_name) {
+            Ok(path) => path,
+            Err(_) => return Vec::new(),
+        };
+
+        self.packs.iter().rev()
+            .filter(|pack| pack.source.contains(&path))
+            .map(|pack| pack.name.as_str())
+            .collect()
+    }
+
+    /**
+    The current version of a resource: which pack supplies it, and when it was last modified there.
+    Comparing versions tells us if a resource has been edited, or overridden by a different pack.
+    Returns `None` if no pack contains the resource.
+    */
+    pub fn version_of(&self, resource_name: &str) -> Option<(String, Option<SystemTime>)> {
+        let path = ResourcePath::new(resource_name).ok()?;
+        self.find_pack(&path)
+            .map(|pack| (pack.name.clone(), pack.source.modified(&path)))
+    }
+
+    /**
+    Names of every resource in a folder (and its sub-folders) across all the packs, sorted.
+    A resource in several packs is only listed once.
+    */
+    pub fn list(&self, folder: &str) -> Vec<String> {
+        let prefix = match ResourcePath::new(folder) {
+            Ok(path) => format!("{}/", path.key()),
+            Err(_) => return Vec::new(),
+        };
+
+        let mut names: Vec<ResourcePath> = Vec::new();
+        for pack in self.packs.iter().rev() {
+            for path in pack.source.list() {
+                if path.key().starts_with(&prefix) && !names.contains(&path) {
+                    names.push(path);
+                }
+            }
+        }
+
+        let mut names: Vec<String> = names.iter().map(|path| String::from(path.as_str())).collect();
+        names.sort();
+        names
+    }
+
+    // -- Instance methods -- //
+    // Loads a file as raw bytes
+    pub fn load_bytes(&self, resource_name: &str) -> Result<Vec<u8>, Error> {
+        let path = ResourcePath::new(resource_name)?;
+        self.find_pack(&path)
+            .ok_or_else(|| Error::NotFound(String::from(path.as_str())))?
+            .source.read(&path)
+    }
+
+    // Loads a file as a CString
+    pub fn load_cstring(&self, resource_name: &str) -> Result<ffi::CString, Error> {
+        let mut buffer = self.load_bytes(resource_name)?;
+        buffer.reserve_exact(1);    // Room for the null terminator
+
+        // check for null byte
+        // if buffer.iter().find(|i| **i == 0).is_some() {
+        if buffer.iter().find(|&&i| i == 0).is_some() {
+            return Err(Error::FileContainsNullByte);
+        }
+
+        Ok(unsafe { ffi::CString::from_vec_unchecked(buffer) })
+    }
+
+
+    /**
+    Loads and decodes an image. The format is detected from the file contents or extension:
+    PNG, JPEG, TGA, BMP, and uncompressed DDS and KTX are supported.
+    */
+    pub fn load_image(&self, image_name : &str) -> Result<DecodedImage, Error> {
+        DecodedImage::decode(image_name, self.load_bytes(image_name)?)
+    }
+
+    // -- Helpers -- //
+    // Find the highest priority pack that contains the resource
+    fn find_pack(&self, path: &ResourcePath) -> Option<&Pack> {
+        self.packs.iter().rev()
+            .find(|pack| pack.source.contains(path))
+    }
+}
+
+// Get the directory containing the current executable
+fn exe_dir() -> Result<PathBuf, Error> {
+    let exe_file_name = ::std::env::current_exe()   // Get path of current executable
+        .map_err(|_| Error::FailedToGetExePath)?;   // Map any errors to our "FailedTOGetExePath" error
+    // Get the directory:
+    let exe_path = exe_file_name.parent().ok_or(Error::FailedToGetExePath)?;    // `ok_or`: If no parent, throw error
+
+    Ok(exe_path.into())
+}
+
+fn embedded_pack() -> Pack {
+    Pack {
+        name: String::from("embedded"),
+        source: Box::new(EmbeddedSource::new()),
+    }
+}
+
+// Open a path as a resource source: archives are read as zips, anything else as a folder
+fn open_source(path: &Path) -> Result<Box<dyn Source>, Error> {
+    if archive::is_archive(path) {
+        Ok(Box::new(ZipSource::open(path)?))
+    } else {
+        Ok(Box::new(DirSource::new(path)))
+    }
+}
@@ -0,0 +1,133 @@
+/*
+Resource names are untrusted (they can come from resource packs and manifests), so they're validated
+before any source sees them. A `ResourcePath` is always a relative, posix style path that stays inside
+the root of whatever source it's looked up in.
+
+Names are matched case insensitively, so a pack behaves the same whether it's a folder on a case
+sensitive file system, a folder on Windows, or a zip.
+*/
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use super::Error;
+
+#[derive(Clone, Debug)]
+pub struct ResourcePath {
+    path: String,   // Normalised, eg/ `./shaders//x.vert` is rejected, `./shaders/x.vert` becomes `shaders/x.vert`
+    key: String,    // Lowercase version of `path`, used for lookups
+}
+
+impl ResourcePath {
+    /**
+    Validate and normalise a resource name.
+    `.` segments are dropped. Absolute paths, `..` segments, empty segments, backslashes and drive letters
+    (or anything else with a `:`) are rejected.
+    */
+    pub fn new(name: &str) -> Result<ResourcePath, Error> {
+        let invalid = |reason| Err(Error::InvalidPath { name: String::from(name), reason });
+
+        if name.is_empty() {
+            return invalid("the name is empty");
+        }
+        if name.starts_with('/') {
+            return invalid("absolute paths are not allowed");
+        }
+        if name.contains('\\') {
+            return invalid("backslashes are not allowed, use `/` to separate folders");
+        }
+        if name.contains(':') {
+            return invalid("`:` is not allowed");
+        }
+        if name.chars().any(char::is_control) {
+            return invalid("control characters are not allowed");
+        }
+
+        let mut segments = Vec::new();
+        for segment in name.split('/') {
+            match segment {
+                "" => return invalid("empty folder names are not allowed"),
+                "." => {},
+                ".." => return invalid("`..` is not allowed"),
+                _ => segments.push(segment),
+            }
+        }
+        if segments.is_empty() {
+            return invalid("the name doesn't refer to a file");
+        }
+
+        let path = segments.join("/");
+        Ok(ResourcePath {
+            key: path.to_lowercase(),
+            path,
+        })
+    }
+
+    // The normalised name
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    // The name used to compare paths: two paths with the same key are the same resource
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    // Each folder name, then the file name
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.path.split('/')
+    }
+
+    /**
+    Resolve a name relative to the folder this path is in, eg/ `common/quat.glsl` relative to `shaders/triangle.vert`
+    is `shaders/common/quat.glsl`. Here `..` is allowed, as long as it doesn't lead above the root.
+    */
+    pub fn relative(&self, name: &str) -> Result<ResourcePath, Error> {
+        let invalid = |reason| Err(Error::InvalidPath { name: String::from(name), reason });
+
+        let mut segments: Vec<&str> = self.segments().collect();
+        segments.pop();     // The file name
+        for segment in name.split('/') {
+            match segment {
+                "" => return invalid("empty folder names are not allowed"),
+                ".." => if segments.pop().is_none() {
+                    return invalid("`..` leads outside of the resources");
+                },
+                _ => segments.push(segment),
+            }
+        }
+
+        ResourcePath::new(&segments.join("/"))
+    }
+
+    // The path under a folder on disk. The segments are joined one at a time, so this works on windows too.
+    pub fn to_path(&self, root_dir: &Path) -> PathBuf {
+        let mut path: PathBuf = root_dir.into();
+        for segment in self.segments() {
+            path.push(segment);
+        }
+
+        path
+    }
+}
+
+impl PartialEq for ResourcePath {
+    fn eq(&self, other: &ResourcePath) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for ResourcePath {}
+
+impl Hash for ResourcePath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl fmt::Display for ResourcePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path)
+    }
+}
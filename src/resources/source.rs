@@ -0,0 +1,121 @@
+/*
+Sources are the places a pack can read its resources from.
+Resources are looked up by `ResourcePath`, so sources only ever see validated names relative to their root
+(eg/ `shaders/triangle.vert`), and should match them case insensitively.
+*/
+
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::time::SystemTime;
+use super::Error;
+use super::path::ResourcePath;
+
+// Sources are shared with the background loading threads, so must be thread safe
+pub trait Source: Send + Sync {
+    // Whether the source contains the named resource
+    fn contains(&self, path: &ResourcePath) -> bool;
+
+    // Read the whole of the named resource
+    fn read(&self, path: &ResourcePath) -> Result<Vec<u8>, Error>;
+
+    // When the named resource was last modified, if the source can tell
+    fn modified(&self, _path: &ResourcePath) -> Option<SystemTime> {
+        None
+    }
+
+    // Every resource in the source
+    fn list(&self) -> Vec<ResourcePath>;
+}
+
+// -- Directory -- //
+/**
+A folder on disk
+*/
+pub struct DirSource {
+    root_path: PathBuf,
+}
+
+impl DirSource {
+    pub fn new(root_path: &Path) -> DirSource {
+        DirSource {
+            root_path: root_path.into(),
+        }
+    }
+}
+
+impl DirSource {
+    /**
+    Find the file for a resource. Each segment is matched exactly if possible, otherwise case insensitively.
+    Returns `None` if there's no such file, or it's outside the folder (eg/ through a symlink).
+    */
+    fn resolve(&self, path: &ResourcePath) -> Option<PathBuf> {
+        let mut resolved = self.root_path.clone();
+        for segment in path.segments() {
+            let exact = resolved.join(segment);
+            if exact.exists() {
+                resolved = exact;
+                continue;
+            }
+
+            let segment = segment.to_lowercase();
+            resolved = fs::read_dir(&resolved).ok()?
+                .filter_map(|entry| entry.ok())
+                .find(|entry| entry.file_name().to_str().map(|name| name.to_lowercase() == segment).unwrap_or(false))?
+                .path();
+        }
+
+        let resolved = resolved.canonicalize().ok()?;
+        let root = self.root_path.canonicalize().ok()?;
+        if resolved.starts_with(root) && resolved.is_file() {
+            Some(resolved)
+        } else {
+            None
+        }
+    }
+}
+
+impl Source for DirSource {
+    fn contains(&self, path: &ResourcePath) -> bool {
+        self.resolve(path).is_some()
+    }
+
+    fn read(&self, path: &ResourcePath) -> Result<Vec<u8>, Error> {
+        let file_path = self.resolve(path)
+            .ok_or_else(|| Error::NotFound(String::from(path.as_str())))?;
+        Ok(fs::read(file_path)?)
+    }
+
+    fn modified(&self, path: &ResourcePath) -> Option<SystemTime> {
+        fs::metadata(self.resolve(path)?)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn list(&self) -> Vec<ResourcePath> {
+        let mut paths = Vec::new();
+        list_dir(&self.root_path, "", &mut paths);
+        paths
+    }
+}
+
+// Add every file in a folder (and its sub-folders) to `paths`. Symlinks aren't followed, as they may leave the root.
+fn list_dir(dir: &Path, prefix: &str, paths: &mut Vec<ResourcePath>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let file_name = match entry.file_name().into_string() {
+            Ok(file_name) => file_name,
+            Err(_) => continue,     // Not valid unicode, so can't be a resource name
+        };
+        let name = format!("{}{}", prefix, file_name);
+
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => list_dir(&entry.path(), &format!("{}/", name), paths),
+            Ok(file_type) if file_type.is_file() => paths.extend(ResourcePath::new(&name).ok()),
+            _ => {},
+        }
+    }
+}
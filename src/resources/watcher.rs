@@ -0,0 +1,56 @@
+/*
+Polls the resources we care about, so we can react when they're edited on disk.
+*/
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+use super::Resources;
+
+type Version = Option<(String, Option<SystemTime>)>;
+
+pub struct Watcher {
+    interval: Duration,         // Minimum time between checks
+    last_poll: Instant,
+    versions: HashMap<String, Version>,   // Resource name -> last seen version
+}
+
+impl Watcher {
+    pub fn new(interval: Duration) -> Watcher {
+        Watcher {
+            interval,
+            last_poll: Instant::now(),
+            versions: HashMap::new(),
+        }
+    }
+
+    // Start watching a resource. Changes are reported relative to its current version.
+    pub fn watch(&mut self, res: &Resources, resource_name: &str) {
+        self.versions.insert(String::from(resource_name), res.version_of(resource_name));
+    }
+
+    pub fn unwatch(&mut self, resource_name: &str) {
+        self.versions.remove(resource_name);
+    }
+
+    /**
+    Get the names of all watched resources that have changed since the last poll.
+    This is cheap to call every frame: the files are only checked once per `interval`.
+    */
+    pub fn poll(&mut self, res: &Resources) -> Vec<String> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+        for (name, version) in self.versions.iter_mut() {
+            let current = res.version_of(name);
+            if current != *version {
+                *version = current;
+                changed.push(name.clone());
+            }
+        }
+
+        changed
+    }
+}
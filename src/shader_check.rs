@@ -0,0 +1,143 @@
+/*
+`rusty-craft shader-check [pack...]`: compile and link every program without starting the game, so broken shaders
+are caught early (eg/ when checking a resource pack submission). Any packs given (folders or `.zip` archives) are
+added on top of the usual assets. Exits with an error if anything fails to build.
+
+The context comes from a hidden window, so this works anywhere with an OpenGL 3.3 driver, including Mesa's
+software renderer (`LIBGL_ALWAYS_SOFTWARE=1`) on machines without a GPU.
+
+What's checked:
+- every program in the manifest, plus each set of defines its materials use
+- every other shader in `shaders/`: `.vert`, `.geom` and `.frag` files with the same name are linked together,
+  `.comp` and `.glsl` files on their own. `.glsl` files without `#stage` markers are includes, so are only checked
+  through the shaders that include them.
+*/
+
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use crate::manifest::Manifest;
+use crate::render::{self, preprocess, shader};
+use crate::render::shader::Shader;
+use crate::render::variants;
+use crate::resources::Resources;
+
+const MANIFEST: &str = "manifest.toml";
+const SHADER_DIR: &str = "shaders";
+
+pub fn run(packs: &[String]) -> Result<(), Box<dyn Error>> {
+    let sdl = sdl2::init()?;
+    let video_subsystem = sdl.video()?;
+    let window = crate::create_window(&video_subsystem, "Rusty-craft shader check", true)?;
+    let _gl_context = window.gl_create_context()?;
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+
+    let mut resources = crate::load_resources()?;
+    for pack in packs {
+        resources.add_pack(pack, Path::new(pack))?;
+    }
+
+    let checks = programs_to_check(&resources)?;
+    let mut failed = 0;
+    for check in checks.iter() {
+        match check.build(&resources) {
+            Ok(()) => println!("ok       {}", check),
+            // Compute shaders need OpenGL 4.3, so can't be checked everywhere
+            Err(render::Error::Unsupported { feature, .. }) => println!("skipped  {} (needs {})", check, feature),
+            Err(e) => {
+                failed += 1;
+                println!("FAILED   {}\n{}\n", check, e);
+            },
+        }
+    }
+
+    println!("Checked {} programs, {} failed", checks.len(), failed);
+    if failed > 0 {
+        return Err(format!("{} of {} programs failed to build", failed, checks.len()).into());
+    }
+
+    Ok(())
+}
+
+// A program to build: its shaders, and the defines to compile them with
+#[derive(PartialEq)]
+struct Check {
+    shader_names: Vec<String>,
+    defines: Vec<String>,
+}
+
+impl Check {
+    fn build(&self, res: &Resources) -> Result<(), render::Error> {
+        variants::build_variant(res, &self.shader_names, &self.defines, None).map(|_| ())
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.shader_names.join(" + "))?;
+        if !self.defines.is_empty() {
+            write!(f, " [{}]", self.defines.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Work out every program to build, from the manifest and the shader folder
+fn programs_to_check(res: &Resources) -> Result<Vec<Check>, Box<dyn Error>> {
+    let mut checks = Vec::new();
+
+    if res.provider_of(MANIFEST).is_some() {
+        let manifest = Manifest::from_resource(res, MANIFEST)?;
+        for entry in manifest.programs.values() {
+            checks.push(Check { shader_names: entry.shaders.clone(), defines: Vec::new() });
+        }
+        for entry in manifest.materials.values() {
+            let check = Check {
+                shader_names: manifest.program(&entry.program)?.shaders.clone(),
+                defines: variants::define_set(&entry.defines),
+            };
+            if !checks.contains(&check) {
+                checks.push(check);
+            }
+        }
+    }
+
+    // Shaders the manifest doesn't use. Names are compared case insensitively, like resources are.
+    let used: HashSet<String> = checks.iter()
+        .flat_map(|check| check.shader_names.iter().map(|name| name.to_lowercase()))
+        .collect();
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();    // Name without extension -> shaders
+    for name in res.list(SHADER_DIR) {
+        if used.contains(&name.to_lowercase()) {
+            continue;
+        }
+
+        if name.ends_with(shader::MULTI_STAGE_EXT) {
+            // Files that can't be read are still checked, so the error is reported
+            let is_include = preprocess::load(res, &name)
+                .map(|source| preprocess::split_stages(&source).is_empty())
+                .unwrap_or(false);
+            if !is_include {
+                groups.insert(name.clone(), vec![name]);
+            }
+            continue;
+        }
+
+        match Shader::type_from_name(&name) {
+            Ok(gl::COMPUTE_SHADER) => { groups.insert(name.clone(), vec![name]); },
+            Ok(_) => {
+                let stem = name[..name.rfind('.').unwrap_or(name.len())].to_lowercase();
+                groups.entry(stem).or_default().push(name);
+            },
+            Err(_) => {},   // Not a shader (eg/ a readme)
+        }
+    }
+
+    for (_, shader_names) in groups {
+        checks.push(Check { shader_names, defines: Vec::new() });
+    }
+
+    Ok(checks)
+}